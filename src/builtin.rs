@@ -1,11 +1,11 @@
-use crate::{environment::Environment, expression::*, object::*, statement::*, interpreter::errors::InterpreterError};
+use crate::{environment::Environment, expression::*, function::{Argument, Parameter}, iterator::ScriptIterator, object::*, statement::*, interpreter::errors::{InterpreterError, InterpreterErrorType}, token::Span};
 
 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 pub fn create_builtins(env: &mut Environment) {
-    env.create_internal_function("print", vec!["target"], |inpr| {
-        let val = inpr.env.get("target").unwrap();
+    env.create_internal_function("print", vec![Parameter::new("target")], |inpr| {
+        let val = inpr.env.get("target").unwrap_or(ScriptValue::None);
         println!("{}", val);
         Ok(StatementValue::Normal(ScriptValue::Unit))
     });
@@ -14,43 +14,63 @@ pub fn create_builtins(env: &mut Environment) {
         Ok(StatementValue::Normal(ScriptValue::Object(Object::new())))
     });
 
-    env.create_internal_function("List", vec!["size"], |inpr| {
-        let size = match inpr.env.get("size") {
-            Some(ScriptValue::Number(n)) => n as usize,
-            Some(other) => return Err(InterpreterError::other(&other, "Not a size")),
-            None => 0,
+    env.create_internal_function("Map", Vec::new(), |_| {
+        Ok(StatementValue::Normal(ScriptValue::Map(Rc::new(RefCell::new(HashMap::new())))))
+    });
+
+    env.create_internal_function("List", vec![Parameter::optional("size")], |inpr| {
+        // Sizes have to be whole numbers, like indexes
+        let value = inpr.env.get("size").unwrap_or(ScriptValue::None);
+        let size = match value {
+            ScriptValue::None => 0,
+            ScriptValue::Number(n) if n.fract() == 0.0 && n >= 0.0 => n as usize,
+            _ => return Err(InterpreterError::new(&value, InterpreterErrorType::InvalidSize)),
         };
 
-        Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(
-            vec![ScriptValue::None; size],
-        )))))
+        // Reserving first turns sizes too large to allocate into an error
+        let mut list = Vec::new();
+        if list.try_reserve_exact(size).is_err() {
+            return Err(InterpreterError::new(&value, InterpreterErrorType::InvalidSize));
+        }
+        list.resize(size, ScriptValue::None);
+
+        Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(list)))))
     });
 
-    env.create_internal_function("map", vec!["func", "list"], |inpr| {
+    env.create_internal_function("instanceof", vec![Parameter::new("value"), Parameter::new("class")], |inpr| {
+        let class = match inpr.env.get("class") {
+            Some(ScriptValue::Class(class)) => class,
+            other => {
+                let other = other.unwrap_or(ScriptValue::None);
+                return Err(InterpreterError::new(&other, InterpreterErrorType::NotClass));
+            }
+        };
+
+        let instance = match inpr.env.get("value") {
+            Some(ScriptValue::Object(obj)) => obj.borrow().class().is_some_and(|own| own.is_subclass_of(&class)),
+            _ => false,
+        };
+        Ok(StatementValue::Normal(ScriptValue::Boolean(instance)))
+    });
+
+    env.create_internal_function("map", vec![Parameter::new("func"), Parameter::new("list")], |inpr| {
         let func = match inpr.env.get("func") {
             Some(ScriptValue::Function(f)) => f,
-            Some(other) => panic!("Not a function {:?}", other),
-            None => panic!("Expected args"),
+            other => {
+                let other = other.unwrap_or(ScriptValue::None);
+                return Err(InterpreterError::new(&other, InterpreterErrorType::NotCallable));
+            }
         };
 
-        let list = match inpr.env.get("list") {
-            Some(ScriptValue::List(l)) => l,
-            Some(other) => panic!("Not a list {:?}", other),
-            None => panic!("Expected 2 args"),
-        };
+        let iterable = inpr.env.get("list").unwrap_or(ScriptValue::None);
+        let mut iterator = ScriptIterator::new(inpr, &iterable, Span::default())?;
 
-        let mapped: Result<Vec<ScriptValue>, InterpreterError> = list
-            .borrow()
-            .iter()
-            .map(|e| {
-                func.borrow_mut()
-                    .call(inpr, &vec![Box::new(e.clone()) as Box<dyn Expression>])
-            })
-            .collect();
-
-        match mapped {
-            Ok(result) => Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(result))))),
-            Err(error) => Err(error)
+        let mut result = Vec::new();
+        while let Some(item) = iterator.next_item(inpr)? {
+            let mapped = func.borrow().call(inpr, &[Argument::positional(Box::new(item))], Span::default())?;
+            result.push(mapped);
         }
+
+        Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(result)))))
     });
 }
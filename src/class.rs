@@ -0,0 +1,134 @@
+use crate::{
+    environment::Environment,
+    expression::*,
+    function::*,
+    interpreter::{errors::*, ExpressionResult, Interpreter},
+    object::*,
+    token::Span,
+};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+};
+
+/// A class declared with `class Name extends Base { ... }`. Calling it
+/// creates an instance, an object whose missing fields are looked up from
+/// the methods of the class and its superclasses.
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<RefCell<Function>>>,
+}
+
+impl Class {
+    /// Method `name` of the class or the closest superclass defining it,
+    /// along with that class.
+    pub fn find_method(self: &Rc<Self>, name: &str) -> Option<(Rc<RefCell<Function>>, Rc<Class>)> {
+        match self.methods.get(name) {
+            Some(method) => Some((Rc::clone(method), Rc::clone(self))),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    /// Whether the class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Rc<Class>) -> bool {
+        std::ptr::eq(self, other.as_ref())
+            || self.superclass.as_ref().is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    /// Creates an instance and calls its `init` method with `args`.
+    pub fn construct(self: &Rc<Self>, interpreter: &mut Interpreter, args: &[Argument], span: Span) -> ExpressionResult {
+        let this = ScriptValue::Object(Object::instance(Rc::clone(self)));
+
+        match self.find_method("init") {
+            Some((init, class)) => {
+                let receiver = Receiver {
+                    this: this.clone(),
+                    class: Some(class),
+                };
+                init.borrow().call_method(interpreter, Some(&receiver), args, span)?;
+            }
+            None if !args.is_empty() => {
+                let name = ScriptValue::String(Rc::new(RefCell::new(self.name.clone())));
+                let err_type = InterpreterErrorType::Arity {
+                    min: 0,
+                    max: Some(0),
+                    found: args.len(),
+                };
+                return Err(InterpreterError::new(&name, err_type).at(span));
+            }
+            None => (),
+        }
+
+        Ok(this)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Class {}", self.name)
+    }
+}
+
+/// What a method is called on: `self`, and the class defining the method,
+/// whose superclass `super` refers to.
+#[derive(Debug, Clone)]
+pub struct Receiver {
+    pub this: ScriptValue,
+    pub class: Option<Rc<Class>>,
+}
+
+impl Receiver {
+    /// Defines `self`, and `super` if there is a superclass, in the
+    /// current scope of `env`.
+    pub fn bind(&self, env: &mut Environment) {
+        env.put_new("self", self.this.clone());
+        if let Some(superclass) = self.class.as_ref().and_then(|class| class.superclass.as_ref()) {
+            let methods = Super {
+                class: Rc::clone(superclass),
+                this: self.this.clone(),
+            };
+            env.put_new("super", ScriptValue::Object(Rc::new(RefCell::new(methods))));
+        }
+    }
+}
+
+/// What `super` refers to in a method: the methods of the superclass,
+/// bound to the same instance.
+#[derive(Debug)]
+struct Super {
+    class: Rc<Class>,
+    this: ScriptValue,
+}
+
+impl ObjectLike for Super {
+    fn get(&self, _: &str) -> Option<ScriptValue> {
+        None
+    }
+
+    fn set(&mut self, _: String, _: ScriptValue) -> Result<(), InterpreterError> {
+        Err(InterpreterError::new(&self.this, InterpreterErrorType::ReadOnly))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn class(&self) -> Option<Rc<Class>> {
+        Some(Rc::clone(&self.class))
+    }
+
+    fn receiver(&self) -> Option<ScriptValue> {
+        Some(self.this.clone())
+    }
+}
+
+impl Display for Super {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "super of {}", self.this)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::expression::*;
 use crate::function::*;
+use crate::interpreter::errors::{InterpreterError, InterpreterErrorType};
 use crate::statement::*;
 use std::collections::HashMap;
 
@@ -12,7 +13,7 @@ pub struct Env {
 }
 
 impl Env {
-    pub fn put(&mut self, key: String, value: ScriptValue) {
+    pub fn put(&mut self, key: String, value: ScriptValue) -> Result<(), InterpreterError> {
         match self.variables.get(&key) {
             Some(_) => {
                 self.variables.insert(key, value);
@@ -20,11 +21,15 @@ impl Env {
             None => match &self.parent {
                 Some(env) => {
                     let mut parent = env.borrow_mut();
-                    parent.put(key, value);
+                    parent.put(key, value)?;
+                }
+                None => {
+                    let err_type = InterpreterErrorType::UndefinedVariable(key);
+                    return Err(InterpreterError::new(&value, err_type));
                 }
-                None => panic!("Variable {} not defined", key),
             },
         };
+        Ok(())
     }
 
     pub fn put_new(&mut self, key: String, value: ScriptValue) {
@@ -33,7 +38,7 @@ impl Env {
 
     pub fn get(&self, key: &str) -> Option<ScriptValue> {
         match self.variables.get(key) {
-            Some(val) => return Some(val.clone()),
+            Some(val) => Some(val.clone()),
             None => match &self.parent {
                 Some(env) => {
                     let parent = env.borrow();
@@ -69,21 +74,22 @@ impl Environment {
         self.env = next;
     }
 
-    pub fn exit(&mut self) {
+    pub fn exit(&mut self) -> Result<(), InterpreterError> {
         let env = Rc::clone(&self.env);
         let current = env.borrow();
 
         match &current.parent {
             Some(env) => {
-                self.env = Rc::clone(&env);
+                self.env = Rc::clone(env);
+                Ok(())
             }
-            None => panic!("ASDAS"),
+            None => Err(InterpreterError::new(&ScriptValue::Unit, InterpreterErrorType::ScopeUnderflow)),
         }
     }
 
-    pub fn put(&mut self, key: &str, val: ScriptValue) {
+    pub fn put(&mut self, key: &str, val: ScriptValue) -> Result<(), InterpreterError> {
         let mut env = self.env.borrow_mut();
-        env.put(key.to_owned(), val);
+        env.put(key.to_owned(), val)
     }
 
     pub fn put_new(&mut self, key: &str, val: ScriptValue) {
@@ -104,13 +110,14 @@ impl Environment {
     pub fn create_internal_function(
         &mut self,
         name: &str,
-        params: Vec<&str>,
+        params: Vec<Parameter>,
         func: InternalFunction,
     ) {
         self.put_new(
             name,
             ScriptValue::Function(Function::new(
-                params.iter().map(|e| e.to_string()).collect(),
+                name.to_string(),
+                params,
                 Rc::new(InternalStatement { func }),
                 Rc::clone(&self.env),
             )),
@@ -1,16 +1,34 @@
+use crate::class::Class;
 use crate::function::*;
 use crate::interpreter::{Interpreter, ExpressionResult, errors::*};
+use crate::iterator::Range;
+use crate::list;
+use crate::map::{self, Map};
 use crate::object::*;
-use crate::token::{Token, TokenType};
+use crate::statement::Statement;
+use crate::token::{Span, Token, TokenType};
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
 pub trait Expression: fmt::Debug {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult;
+    fn span(&self) -> Span;
+
+    /// Whether the expression can be assigned to, checked by the parser
+    /// before `assign` can be called.
+    fn is_assignable(&self) -> bool {
+        false
+    }
 
     fn assign(&self, _: &mut Interpreter, _: ScriptValue) -> ExpressionResult {
-        panic!("Cannot assign to {:?}", self);
+        Err(InterpreterError::new(&ScriptValue::Unit, InterpreterErrorType::NotAssignable).at(self.span()))
+    }
+
+    /// The expression as a field access, for calls to bind `self` to the
+    /// object the method is called on.
+    fn as_access(&self) -> Option<&AccessExpression> {
+        None
     }
 }
 
@@ -20,9 +38,16 @@ pub trait ExpressionVisitor {
     fn visit_addition(&mut self, expr: &AdditionExpression) -> ExpressionResult;
     fn visit_multiplication(&mut self, expr: &MultiplicationExpression) -> ExpressionResult;
     fn visit_condition(&mut self, expr: &ConditionExpression) -> ExpressionResult;
+    fn visit_logical(&mut self, expr: &LogicalExpression) -> ExpressionResult;
     fn visit_function(&mut self, expr: &FunctionExpression) -> ExpressionResult;
     fn visit_access(&mut self, expr: &AccessExpression) -> ExpressionResult;
     fn visit_index(&mut self, expr: &IndexExpression) -> ExpressionResult;
+    fn visit_interpolation(&mut self, expr: &InterpolationExpression) -> ExpressionResult;
+    fn visit_list(&mut self, expr: &ListExpression) -> ExpressionResult;
+    fn visit_object(&mut self, expr: &ObjectExpression) -> ExpressionResult;
+    fn visit_map(&mut self, expr: &MapExpression) -> ExpressionResult;
+    fn visit_lambda(&mut self, expr: &LambdaExpression) -> ExpressionResult;
+    fn visit_range(&mut self, expr: &RangeExpression) -> ExpressionResult;
 }
 
 #[derive(Debug, Clone)]
@@ -31,10 +56,15 @@ pub enum ScriptValue {
     String(Rc<RefCell<String>>),
     Boolean(bool),
     Function(Rc<RefCell<Function>>),
+    Class(Rc<Class>),
     Object(Rc<RefCell<dyn ObjectLike>>),
     List(Rc<RefCell<Vec<ScriptValue>>>),
+    Map(Map),
+    Range(Rc<Range>),
     None,
     Unit,
+    /// Returned by an iterator's `next()` when it has no more values
+    Done,
 }
 
 impl ScriptValue {
@@ -46,44 +76,68 @@ impl ScriptValue {
                     TokenType::Minus => left - right,
                     TokenType::Star => left * right,
                     TokenType::Slash => left / right,
-                    _ => return Err(InterpreterError::other(&self, "Impossible addition")),
+                    _ => return Err(InterpreterError::other(self, "Impossible addition")),
                 };
 
                 Ok(ScriptValue::Number(result))
             }
             // _ => panic!("Cannot {:?} {:?} and {:?}", operator.token_type, self, other),
-            _ => Err(InterpreterError::other(&self, "Cannot operate"))
+            _ => Err(InterpreterError::other(self, "Cannot operate"))
+        }
+    }
+
+    /// Equality used by `==` and `!=`: values of different types are never
+    /// equal, and functions, objects and lists compare by identity.
+    pub fn equals(&self, other: &ScriptValue) -> bool {
+        match (self, other) {
+            (ScriptValue::Number(left), ScriptValue::Number(right)) => left == right,
+            (ScriptValue::String(left), ScriptValue::String(right)) => {
+                *left.borrow() == *right.borrow()
+            }
+            (ScriptValue::Boolean(left), ScriptValue::Boolean(right)) => left == right,
+            (ScriptValue::Function(left), ScriptValue::Function(right)) => Rc::ptr_eq(left, right),
+            (ScriptValue::Class(left), ScriptValue::Class(right)) => Rc::ptr_eq(left, right),
+            (ScriptValue::Object(left), ScriptValue::Object(right)) => Rc::ptr_eq(left, right),
+            (ScriptValue::List(left), ScriptValue::List(right)) => Rc::ptr_eq(left, right),
+            (ScriptValue::Map(left), ScriptValue::Map(right)) => Rc::ptr_eq(left, right),
+            (ScriptValue::Range(left), ScriptValue::Range(right)) => left == right,
+            (ScriptValue::None, ScriptValue::None) => true,
+            (ScriptValue::Unit, ScriptValue::Unit) => true,
+            (ScriptValue::Done, ScriptValue::Done) => true,
+            _ => false,
         }
     }
 
     pub fn boolean(&self, other: ScriptValue, operator: Token) -> ExpressionResult {
+        match operator.token_type {
+            TokenType::Equals => return Ok(ScriptValue::Boolean(self.equals(&other))),
+            TokenType::NotEquals => return Ok(ScriptValue::Boolean(!self.equals(&other))),
+            _ => (),
+        }
+
         let result = match (self, &other) {
             (ScriptValue::Number(left), ScriptValue::Number(right)) => match operator.token_type {
-                TokenType::Equals => left == right,
-                TokenType::NotEquals => left != right,
                 TokenType::Lesser => left < right,
                 TokenType::Greater => left > right,
                 TokenType::EqLesser => left <= right,
                 TokenType::EqGreater => left >= right,
-                _ => panic!("Impossible boolean operation"),
+                _ => return Err(InterpreterError::other(self, "Impossible comparison")),
             },
-            (ScriptValue::Boolean(left), ScriptValue::Boolean(right)) => match operator.token_type {
-                TokenType::Equals => left == right,
-                TokenType::NotEquals => left != right,
-                TokenType::And => *left && *right,
-                TokenType::Or => *left || *right,
-                _ => panic!("Impossible boolean operation"),
-            },
-            _ => panic!("Cannot compare {:?} and {:?}", self, other),
+            _ => return Err(InterpreterError::new(self, InterpreterErrorType::NotComparable(other))),
         };
 
         Ok(ScriptValue::Boolean(result))
     }
 }
 
+// Values created at runtime have no place in the source
 impl Expression for ScriptValue {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_value(&self)
+        visitor.visit_value(self)
+    }
+
+    fn span(&self) -> Span {
+        Span::default()
     }
 }
 
@@ -93,27 +147,101 @@ impl fmt::Display for ScriptValue {
             ScriptValue::Number(n) => write!(f, "{}", n),
             ScriptValue::Boolean(b) => write!(f, "{}", b),
             ScriptValue::Function(_) => write!(f, "Func"),
-            ScriptValue::Object(o) => write!(f, "{}", o.borrow()),
+            ScriptValue::Class(class) => write!(f, "{}", class),
+            ScriptValue::Object(o) => {
+                nested(f, Rc::as_ptr(o) as *const (), "{...}", |f| write!(f, "{}", o.borrow()))
+            }
             ScriptValue::String(s) => write!(f, "{}", s.borrow()),
-            ScriptValue::List(l) => write!(f, "{:?}", *l.borrow()),
+            ScriptValue::List(l) => nested(f, Rc::as_ptr(l) as *const (), "[...]", |f| {
+                write!(
+                    f,
+                    "[{}]",
+                    l.borrow()
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+            ScriptValue::Map(m) => nested(f, Rc::as_ptr(m) as *const (), "{...}", |f| {
+                write!(
+                    f,
+                    "{{ {} }}",
+                    map::entries(m)
+                        .iter()
+                        .map(|(k, v)| format!("[{}]: {}", k, v))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+            ScriptValue::Range(range) => write!(f, "{}", range),
             ScriptValue::None => write!(f, "null"),
             ScriptValue::Unit => write!(f, "()"),
+            ScriptValue::Done => write!(f, "done"),
         }
     }
 }
 
+thread_local! {
+    // Lists, maps and objects being displayed, to cut off cycles
+    static DISPLAYING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Displays the value at `ptr` with `write`, or as `placeholder` if it is
+/// already being displayed further up, like a list that contains itself.
+fn nested(
+    f: &mut fmt::Formatter<'_>,
+    ptr: *const (),
+    placeholder: &str,
+    write: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    if DISPLAYING.with(|displaying| displaying.borrow().contains(&ptr)) {
+        return write!(f, "{}", placeholder);
+    }
+
+    DISPLAYING.with(|displaying| displaying.borrow_mut().push(ptr));
+    let result = write(f);
+    DISPLAYING.with(|displaying| displaying.borrow_mut().pop());
+    result
+}
+
+#[derive(Debug)]
+pub struct LiteralExpression {
+    pub value: ScriptValue,
+    pub span: Span,
+}
+
+impl Expression for LiteralExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_value(&self.value)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
 #[derive(Debug)]
 pub struct VariableExpression {
     pub identifier: String,
+    pub span: Span,
 }
 
 impl Expression for VariableExpression {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_variable(&self)
+        visitor.visit_variable(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn is_assignable(&self) -> bool {
+        true
     }
 
     fn assign(&self, interpreter: &mut Interpreter, value: ScriptValue) -> ExpressionResult {
-        interpreter.env.put(&self.identifier, value.clone());
+        interpreter.env.put(&self.identifier, value).map_err(|e| e.at(self.span))?;
         Ok(ScriptValue::Unit)
     }
 }
@@ -123,11 +251,36 @@ pub struct ConditionExpression {
     pub left: Box<dyn Expression>,
     pub right: Box<dyn Expression>,
     pub operator: Token,
+    pub span: Span,
 }
 
 impl Expression for ConditionExpression {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_condition(&self)
+        visitor.visit_condition(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// `&&` and `||`, which only evaluate the right side when it can change
+/// the result.
+#[derive(Debug)]
+pub struct LogicalExpression {
+    pub left: Box<dyn Expression>,
+    pub right: Box<dyn Expression>,
+    pub operator: Token,
+    pub span: Span,
+}
+
+impl Expression for LogicalExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_logical(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -136,11 +289,16 @@ pub struct AdditionExpression {
     pub left: Box<dyn Expression>,
     pub right: Box<dyn Expression>,
     pub operator: Token,
+    pub span: Span,
 }
 
 impl Expression for AdditionExpression {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_addition(&self)
+        visitor.visit_addition(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -149,23 +307,33 @@ pub struct MultiplicationExpression {
     pub left: Box<dyn Expression>,
     pub right: Box<dyn Expression>,
     pub operator: Token,
+    pub span: Span,
 }
 
 impl Expression for MultiplicationExpression {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_multiplication(&self)
+        visitor.visit_multiplication(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
 #[derive(Debug)]
 pub struct FunctionExpression {
     pub expr: Box<dyn Expression>,
-    pub params: Vec<Box<dyn Expression>>,
+    pub params: Vec<Argument>,
+    pub span: Span,
 }
 
 impl Expression for FunctionExpression {
     fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
-        visitor.visit_function(&self)
+        visitor.visit_function(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -173,6 +341,7 @@ impl Expression for FunctionExpression {
 pub struct AccessExpression {
     pub expr: Box<dyn Expression>,
     pub field: String,
+    pub span: Span,
 }
 
 impl Expression for AccessExpression {
@@ -180,14 +349,26 @@ impl Expression for AccessExpression {
         visitor.visit_access(self)
     }
 
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn is_assignable(&self) -> bool {
+        true
+    }
+
+    fn as_access(&self) -> Option<&AccessExpression> {
+        Some(self)
+    }
+
     fn assign(&self, interpreter: &mut Interpreter, value: ScriptValue) -> ExpressionResult {
         let target = self.expr.accept(interpreter)?;
         match target {
             ScriptValue::Object(obj) => {
-                Object::set_ref(obj, self.field.clone(), value);
+                obj.borrow_mut().set(self.field.clone(), value).map_err(|e| e.at(self.span))?;
                 Ok(ScriptValue::Unit)
             }
-            _ => panic!("{:?} is not an object", target),
+            _ => Err(InterpreterError::new(&target, InterpreterErrorType::NotObject).at(self.span)),
         }
     }
 }
@@ -196,6 +377,7 @@ impl Expression for AccessExpression {
 pub struct IndexExpression {
     pub expr: Box<dyn Expression>,
     pub index_expr: Box<dyn Expression>,
+    pub span: Span,
 }
 
 impl Expression for IndexExpression {
@@ -203,20 +385,142 @@ impl Expression for IndexExpression {
         visitor.visit_index(self)
     }
 
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn is_assignable(&self) -> bool {
+        true
+    }
+
     fn assign(&self, interpreter: &mut Interpreter, value: ScriptValue) -> ExpressionResult{
         let target = self.expr.accept(interpreter)?;
         match target {
             ScriptValue::List(list) => {
                 let index = self.index_expr.accept(interpreter)?;
-                match index {
-                    ScriptValue::Number(n) => {
-                        list.borrow_mut()[n as usize] = value;
-                        Ok(ScriptValue::Unit)
-                    }
-                    _ => panic!("Cannot use {} as index", index),
-                }
+                let len = list.borrow().len();
+                let position = list::position(&index, len).map_err(|e| e.at(self.index_expr.span()))?;
+                list.borrow_mut()[position] = value;
+                Ok(ScriptValue::Unit)
+            }
+            ScriptValue::Map(map) => {
+                let key = self.index_expr.accept(interpreter)?;
+                let key = map::Key::new(&key).map_err(|e| e.at(self.index_expr.span()))?;
+                map.borrow_mut().insert(key, value);
+                Ok(ScriptValue::Unit)
             }
-            _ => panic!("{:?} is not an object", target),
+            _ => Err(InterpreterError::new(&target, InterpreterErrorType::NotIndexable).at(self.span)),
         }
     }
 }
+
+/// String literal with embedded `${...}` expressions, concatenated using
+/// the `Display` of each part.
+#[derive(Debug)]
+pub struct InterpolationExpression {
+    pub parts: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+impl Expression for InterpolationExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_interpolation(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug)]
+pub struct ListExpression {
+    pub items: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+impl Expression for ListExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_list(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjectExpression {
+    pub fields: Vec<(String, Box<dyn Expression>)>,
+    pub span: Span,
+}
+
+impl Expression for ObjectExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_object(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Map literal, `{ [key]: value, ... }`.
+#[derive(Debug)]
+pub struct MapExpression {
+    pub entries: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+    pub span: Span,
+}
+
+impl Expression for MapExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_map(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Anonymous function, `fn(x) { ... }` or `x => ...`.
+pub struct LambdaExpression {
+    pub params: Vec<Parameter>,
+    pub body: Rc<dyn Statement>,
+    pub span: Span,
+}
+
+impl fmt::Debug for LambdaExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Lambda({})", parameter_names(&self.params))
+    }
+}
+
+impl Expression for LambdaExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_lambda(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// `start..end` or `start..=end`, with an optional `step`. Evaluates to a
+/// range that yields its numbers as it is iterated.
+#[derive(Debug)]
+pub struct RangeExpression {
+    pub start: Box<dyn Expression>,
+    pub end: Box<dyn Expression>,
+    pub inclusive: bool,
+    pub step: Option<Box<dyn Expression>>,
+    pub span: Span,
+}
+
+impl Expression for RangeExpression {
+    fn accept(&self, visitor: &mut dyn ExpressionVisitor) -> ExpressionResult {
+        visitor.visit_range(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
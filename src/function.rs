@@ -1,47 +1,243 @@
-use crate::{environment::*, expression::*, interpreter::*, statement::*};
+use crate::{
+    class::Receiver,
+    environment::*,
+    expression::*,
+    interpreter::{errors::*, *},
+    statement::*,
+    token::Span,
+};
 
 use std::{cell::RefCell, fmt, rc::Rc};
 
+/// A declared parameter. Parameters with a default value can be left out of
+/// a call, and a rest parameter collects the remaining arguments into a list.
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Rc<dyn Expression>>,
+    pub rest: bool,
+}
+
+impl Parameter {
+    pub fn new(name: &str) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            default: None,
+            rest: false,
+        }
+    }
+
+    /// Parameter of an internal function that is `null` when left out.
+    pub fn optional(name: &str) -> Parameter {
+        Parameter {
+            default: Some(Rc::new(ScriptValue::None)),
+            ..Parameter::new(name)
+        }
+    }
+}
+
+/// A call argument, passed by position or by `name`.
+#[derive(Debug)]
+pub struct Argument {
+    pub name: Option<String>,
+    pub expr: Box<dyn Expression>,
+}
+
+impl Argument {
+    pub fn positional(expr: Box<dyn Expression>) -> Argument {
+        Argument { name: None, expr }
+    }
+}
+
+pub fn parameter_names(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .map(|param| if param.rest { format!("...{}", param.name) } else { param.name.clone() })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 pub struct Function {
-    pub params: Vec<String>,
+    pub name: String,
+    pub params: Vec<Parameter>,
     pub body: Rc<dyn Statement>,
     pub env: Environment,
+    /// What `self` is when the function is called on its own, the object it
+    /// was taken from
+    pub this: Option<Receiver>,
 }
 
 impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Func({})", self.params.join(","))
+        write!(f, "Func({})", parameter_names(&self.params))
     }
 }
 
 impl Function {
     pub fn new(
-        params: Vec<String>,
+        name: String,
+        params: Vec<Parameter>,
         body: Rc<dyn Statement>,
         env: Rc<RefCell<Env>>,
     ) -> Rc<RefCell<Function>> {
         let env = Environment { env };
-        Rc::new(RefCell::new(Function { params, body, env }))
+        Rc::new(RefCell::new(Function {
+            name,
+            params,
+            body,
+            env,
+            this: None,
+        }))
     }
 
-    pub fn call(&self, base: &mut Interpreter, params: &Vec<Box<dyn Expression>>) -> ExpressionResult {
+    /// The function as a method of `receiver`, for when it is taken from an
+    /// object without calling it. Shares the body and environment.
+    pub fn bound(&self, receiver: Receiver) -> Rc<RefCell<Function>> {
+        Rc::new(RefCell::new(Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: Rc::clone(&self.body),
+            env: self.env.clone(),
+            this: Some(receiver),
+        }))
+    }
+
+    /// Binds `args` to the parameters in the scope of `interpreter`.
+    /// Positional arguments go in order, with the extra ones in the rest
+    /// parameter, and named ones go to the parameter of that name. Default
+    /// values are evaluated in the new scope, so they can use earlier
+    /// parameters.
+    fn bind(&self, interpreter: &mut Interpreter, args: Vec<(Option<String>, ScriptValue)>) -> Result<(), InterpreterError> {
+        let name = ScriptValue::String(Rc::new(RefCell::new(self.name.clone())));
+        let error = |err_type| InterpreterError::new(&name, err_type);
+
+        let required = self.params.iter().filter(|param| param.default.is_none() && !param.rest).count();
+        let positional = self.params.iter().filter(|param| !param.rest).count();
+        let max = match self.params.last() {
+            Some(param) if param.rest => None,
+            _ => Some(positional),
+        };
+        let found = args.len();
+        let arity = || error(InterpreterErrorType::Arity { min: required, max, found });
+
+        let mut values: Vec<Option<ScriptValue>> = vec![None; self.params.len()];
+        let mut rest = Vec::new();
+        for (i, (arg_name, value)) in args.into_iter().enumerate() {
+            match arg_name {
+                None if i < positional => values[i] = Some(value),
+                None if max.is_none() => rest.push(value),
+                None => return Err(arity()),
+                Some(arg_name) => {
+                    let index = match self.params.iter().position(|param| !param.rest && param.name == arg_name) {
+                        Some(index) => index,
+                        None => return Err(error(InterpreterErrorType::UnknownArgument(arg_name))),
+                    };
+                    if values[index].is_some() {
+                        return Err(error(InterpreterErrorType::DuplicateArgument(arg_name)));
+                    }
+                    values[index] = Some(value);
+                }
+            }
+        }
+
+        for (param, value) in self.params.iter().zip(values) {
+            let value = match (value, &param.default) {
+                (Some(value), _) => value,
+                _ if param.rest => ScriptValue::List(Rc::new(RefCell::new(std::mem::take(&mut rest)))),
+                (None, Some(default)) => default.accept(interpreter)?,
+                (None, None) if found < required => return Err(arity()),
+                (None, None) => return Err(error(InterpreterErrorType::MissingArgument(param.name.clone()))),
+            };
+            interpreter.env.put_new(&param.name, value);
+        }
+
+        Ok(())
+    }
+
+    /// Calls the function from `span`, evaluating the arguments in `base`.
+    pub fn call(&self, base: &mut Interpreter, args: &[Argument], span: Span) -> ExpressionResult {
+        self.call_method(base, None, args, span)
+    }
+
+    /// Calls the function as a method of `receiver`, or of the object it was
+    /// taken from if called on its own. Built-in functions keep the `self`
+    /// they were created with, like list methods do their list.
+    pub fn call_method(
+        &self,
+        base: &mut Interpreter,
+        receiver: Option<&Receiver>,
+        args: &[Argument],
+        span: Span,
+    ) -> ExpressionResult {
+        let mut values = Vec::new();
+        for arg in args {
+            values.push((arg.name.clone(), arg.expr.accept(base)?));
+        }
+
+        // Calls made by the interpreter itself, like an iterator's `next()`,
+        // have no place in the source, so the enclosing call's is used
+        let span = match base.frames.last() {
+            Some(frame) if span == Span::default() => frame.span,
+            _ => span,
+        };
+
         let mut interpreter = Interpreter {
             env: self.env.clone(),
+            frames: std::mem::take(&mut base.frames),
         };
+        interpreter.frames.push(Frame {
+            name: self.name.clone(),
+            span,
+        });
+
         interpreter.env.enter();
-        for i in 0..self.params.len() {
-            let val = params[i].accept(base)?;
-            let key = self.params[i].clone();
-            interpreter.env.put_new(&key, val);
+        let receiver = match (receiver, &self.this) {
+            // `super` still refers to the class the method came from
+            (Some(receiver), Some(bound)) => Some(Receiver {
+                this: receiver.this.clone(),
+                class: receiver.class.clone().or_else(|| bound.class.clone()),
+            }),
+            (receiver, bound) => receiver.or(bound.as_ref()).cloned(),
+        };
+        match receiver {
+            Some(receiver) if !self.body.is_internal() => receiver.bind(&mut interpreter.env),
+            _ => (),
         }
-        let val = (*self.body).accept(&mut interpreter)?;
-        interpreter.env.exit();
+        let res = self
+            .bind(&mut interpreter, values)
+            .and_then(|_| (*self.body).accept(&mut interpreter))
+            .map_err(|e| e.traced(&interpreter.frames).at(span));
+
+        interpreter.frames.pop();
+        base.frames = interpreter.frames;
+        let val = res?;
+        interpreter.env.exit()?;
 
         Ok(
             match val {
                 StatementValue::Normal(x) => x,
                 StatementValue::Return(x) => x,
+                // Loop control cannot leave a function, the parser rejects it
+                StatementValue::Break(_) | StatementValue::Continue(_) => ScriptValue::Unit,
             }
         )
     }
 }
+
+/// Built-in method `name` of `this`, which it gets as `self`.
+pub fn internal_method(name: &str, params: Vec<&str>, func: InternalFunction, this: ScriptValue) -> ScriptValue {
+    let mut env = Environment::new();
+    env.put_new("self", this);
+
+    ScriptValue::Function(Function::new(
+        name.to_string(),
+        params.into_iter().map(Parameter::new).collect(),
+        Rc::new(InternalStatement { func }),
+        env.env,
+    ))
+}
+
+/// Argument `name` of a built-in function, `null` if it was left out.
+pub fn arg(inpr: &Interpreter, name: &str) -> ScriptValue {
+    inpr.env.get(name).unwrap_or(ScriptValue::None)
+}
@@ -1,47 +1,216 @@
 use std::fmt;
-use super::ScriptValue;
+use std::iter;
+use std::{cell::RefCell, rc::Rc};
+use super::{Frame, ScriptValue};
+use crate::object::Object;
+use crate::token::Span;
 #[derive(Debug,Clone)]
 pub enum InterpreterErrorType {
     UndefinedVariable(String),
     NotCallable,
+    NotClass,
     NotObject,
     PropertyNotFound,
     InvalidIndex,
     NotIndexable,
+    NotIterable,
+    NotBoolean,
+    NotNumber,
+    NotList,
+    NotMap,
+    IndexOutOfBounds,
+    InvalidRangeBound,
+    ZeroStep,
+    InvalidSize,
+    KeyNotFound,
+    NotHashable,
+    /// Comparison of `target` with the value kept here
+    NotComparable(ScriptValue),
+    NotAssignable,
+    ReadOnly,
+    ScopeUnderflow,
+    /// Call of the function named in `target` with `found` arguments, when
+    /// it takes from `min` to `max`, or any number above `min` without a `max`
+    Arity { min: usize, max: Option<usize>, found: usize },
+    UnknownArgument(String),
+    DuplicateArgument(String),
+    MissingArgument(String),
+    /// A value thrown by the script, kept in `target`
+    Thrown,
     Other(String)
 }
 
 #[derive(Debug,Clone)]
 pub struct InterpreterError {
     pub target: ScriptValue,
-    pub err_type: InterpreterErrorType
+    pub err_type: InterpreterErrorType,
+    pub span: Option<Span>,
+    /// Calls the error happened in, innermost last
+    pub trace: Vec<Frame>,
 }
 
 impl InterpreterError {
     pub fn new(target: &ScriptValue, err_type: InterpreterErrorType) -> InterpreterError {
         InterpreterError {
             target: target.clone(),
-            err_type
+            err_type,
+            span: None,
+            trace: Vec::new(),
         }
     }
 
+    /// Records where the error happened, unless a more precise location
+    /// was already recorded further down.
+    pub fn at(mut self, span: Span) -> InterpreterError {
+        if self.span.is_none() && span != Span::default() {
+            self.span = Some(span);
+        }
+        self
+    }
+
+    /// Records the calls the error happened in, unless they were already
+    /// recorded deeper in the call stack.
+    pub fn traced(mut self, frames: &[Frame]) -> InterpreterError {
+        if self.trace.is_empty() {
+            self.trace = frames.to_vec();
+        }
+        self
+    }
+
+    /// Python-style traceback of the calls the error happened in, most
+    /// recent call last. Errors outside of functions have none.
+    pub fn traceback(&self) -> Option<String> {
+        if self.trace.is_empty() {
+            return None;
+        }
+
+        // Each call site is in the function of the frame before it, and
+        // the error itself is in the innermost function
+        let spans = self.trace.iter().map(|frame| Some(frame.span)).chain(iter::once(self.span));
+        let names = iter::once("<script>").chain(self.trace.iter().map(|frame| frame.name.as_str()));
+
+        let mut traceback = String::from("Traceback (most recent call last):\n");
+        for (span, name) in spans.zip(names) {
+            match span {
+                Some(span) if span != Span::default() => {
+                    traceback.push_str(&format!("  Line {}, col {}, in {}\n", span.line, span.col, name))
+                }
+                _ => traceback.push_str(&format!("  in {}\n", name)),
+            }
+        }
+        Some(traceback)
+    }
+
     pub fn other(target: &ScriptValue, msg: &str) -> InterpreterError {
         InterpreterError::new(target, InterpreterErrorType::Other(msg.to_string()))
     }
+
+    pub fn kind(&self) -> &str {
+        match &self.err_type {
+            InterpreterErrorType::UndefinedVariable(_) => "UndefinedVariable",
+            InterpreterErrorType::NotCallable => "NotCallable",
+            InterpreterErrorType::NotClass => "NotClass",
+            InterpreterErrorType::NotObject => "NotObject",
+            InterpreterErrorType::PropertyNotFound => "PropertyNotFound",
+            InterpreterErrorType::InvalidIndex => "InvalidIndex",
+            InterpreterErrorType::NotIndexable => "NotIndexable",
+            InterpreterErrorType::NotIterable => "NotIterable",
+            InterpreterErrorType::NotBoolean => "NotBoolean",
+            InterpreterErrorType::NotNumber => "NotNumber",
+            InterpreterErrorType::NotList => "NotList",
+            InterpreterErrorType::NotMap => "NotMap",
+            InterpreterErrorType::IndexOutOfBounds => "IndexOutOfBounds",
+            InterpreterErrorType::InvalidRangeBound => "InvalidRangeBound",
+            InterpreterErrorType::ZeroStep => "ZeroStep",
+            InterpreterErrorType::InvalidSize => "InvalidSize",
+            InterpreterErrorType::KeyNotFound => "KeyNotFound",
+            InterpreterErrorType::NotHashable => "NotHashable",
+            InterpreterErrorType::NotComparable(_) => "NotComparable",
+            InterpreterErrorType::NotAssignable => "NotAssignable",
+            InterpreterErrorType::ReadOnly => "ReadOnly",
+            InterpreterErrorType::ScopeUnderflow => "ScopeUnderflow",
+            InterpreterErrorType::Arity { .. } => "Arity",
+            InterpreterErrorType::UnknownArgument(_) => "UnknownArgument",
+            InterpreterErrorType::DuplicateArgument(_) => "DuplicateArgument",
+            InterpreterErrorType::MissingArgument(_) => "MissingArgument",
+            InterpreterErrorType::Thrown => "Thrown",
+            InterpreterErrorType::Other(_) => "Other",
+        }
+    }
+
+    /// The error without its location.
+    pub fn message(&self) -> String {
+        match &self.err_type {
+            InterpreterErrorType::UndefinedVariable(ident) => format!("Variable not found: {}", ident),
+            InterpreterErrorType::NotCallable => format!("Not callable: {}", self.target),
+            InterpreterErrorType::NotClass => format!("Not a class: {}", self.target),
+            InterpreterErrorType::NotObject => format!("Not an object: {}", self.target),
+            InterpreterErrorType::PropertyNotFound => format!("Property not found: {}", self.target),
+            InterpreterErrorType::InvalidIndex => format!("Cannot index with: {}", self.target),
+            InterpreterErrorType::NotIndexable => format!("Not indexable: {}", self.target),
+            InterpreterErrorType::NotIterable => format!("Not iterable: {}", self.target),
+            InterpreterErrorType::NotBoolean => format!("Not a boolean: {}", self.target),
+            InterpreterErrorType::NotNumber => format!("Not a number: {}", self.target),
+            InterpreterErrorType::NotList => format!("Not a list: {}", self.target),
+            InterpreterErrorType::NotMap => format!("Not a map: {}", self.target),
+            InterpreterErrorType::IndexOutOfBounds => format!("Index out of bounds: {}", self.target),
+            InterpreterErrorType::InvalidRangeBound => format!("Not a range bound: {}", self.target),
+            InterpreterErrorType::ZeroStep => "Range step cannot be 0".to_string(),
+            InterpreterErrorType::InvalidSize => format!("Not a list size: {}", self.target),
+            InterpreterErrorType::KeyNotFound => format!("Key not found: {}", self.target),
+            InterpreterErrorType::NotHashable => format!("Cannot use as a key: {}", self.target),
+            InterpreterErrorType::NotComparable(other) => format!("Cannot compare: {} and {}", self.target, other),
+            InterpreterErrorType::NotAssignable => "Cannot assign to expression".to_string(),
+            InterpreterErrorType::ReadOnly => format!("Cannot set property of: {}", self.target),
+            InterpreterErrorType::ScopeUnderflow => "Cannot exit the global scope".to_string(),
+            InterpreterErrorType::Arity { min, max, found } => {
+                let expected = match max {
+                    Some(max) if max == min => min.to_string(),
+                    Some(max) => format!("{} to {}", min, max),
+                    None => format!("at least {}", min),
+                };
+                let plural = if max.unwrap_or(*min) == 1 { "" } else { "s" };
+                format!("{} takes {} argument{}, got {}", self.target, expected, plural, found)
+            }
+            InterpreterErrorType::UnknownArgument(name) => format!("{} has no parameter named {}", self.target, name),
+            InterpreterErrorType::DuplicateArgument(name) => format!("{} got argument {} more than once", self.target, name),
+            InterpreterErrorType::MissingArgument(name) => format!("{} is missing argument {}", self.target, name),
+            InterpreterErrorType::Thrown => format!("Uncaught: {}", self.target),
+            InterpreterErrorType::Other(msg) => format!("{}: {}", msg, self.target),
+        }
+    }
+
+    /// The value a `catch` gets: thrown values as they are, and runtime
+    /// errors as objects with `kind`, `message` and `line` fields.
+    pub fn value(&self) -> ScriptValue {
+        if let InterpreterErrorType::Thrown = self.err_type {
+            return self.target.clone();
+        }
+
+        let string = |s: &str| ScriptValue::String(Rc::new(RefCell::new(s.to_string())));
+        let line = match self.span {
+            Some(span) => ScriptValue::Number(span.line as f64),
+            None => ScriptValue::None,
+        };
+
+        let error = Object::new();
+        {
+            let mut error = error.borrow_mut();
+            error.fields.insert("kind".to_string(), string(self.kind()));
+            error.fields.insert("message".to_string(), string(&self.message()));
+            error.fields.insert("line".to_string(), line);
+        }
+        ScriptValue::Object(error)
+    }
 }
 
 
 impl fmt::Display for InterpreterError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.err_type {
-            InterpreterErrorType::UndefinedVariable(ident) => write!(f, "Variable not found: {}", ident),
-            InterpreterErrorType::NotCallable => write!(f, "Not callable: {}", self.target),
-            InterpreterErrorType::NotObject => write!(f, "Not an object: {}", self.target),
-            InterpreterErrorType::PropertyNotFound => write!(f, "Property not found: {}", self.target),
-            InterpreterErrorType::InvalidIndex => write!(f, "Cannot index with: {}", self.target),
-            InterpreterErrorType::NotIndexable => write!(f, "Not indexable: {}", self.target),
-            InterpreterErrorType::Other(msg) => write!(f, "{}: {}", msg, self.target),
-
+        if let Some(span) = self.span {
+            write!(f, "Line {}, col {}: ", span.line, span.col)?;
         }
+
+        write!(f, "{}", self.message())
     }
 }
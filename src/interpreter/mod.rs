@@ -1,10 +1,18 @@
+use crate::class::{Class, Receiver};
 use crate::environment::*;
 use crate::expression::*;
 use crate::function::*;
+use crate::iterator::{Range, ScriptIterator};
+use crate::list;
+use crate::map::{self, Key};
+use crate::object::*;
 use crate::statement::*;
+use crate::token::{Span, TokenType};
 
 use errors::{InterpreterError, InterpreterErrorType};
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 pub mod errors;
@@ -13,23 +21,66 @@ pub type StatementResult = Result<StatementValue, InterpreterError>;
 pub type ExpressionResult = Result<ScriptValue, InterpreterError>;
 pub struct Interpreter {
     pub env: Environment,
+    /// Function calls in progress, innermost last
+    pub frames: Vec<Frame>,
+}
+
+/// A function call in progress: the called function and where it was
+/// called from.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub span: Span,
 }
 
 impl Interpreter {
+    pub fn new(env: Environment) -> Interpreter {
+        Interpreter {
+            env,
+            frames: Vec::new(),
+        }
+    }
+
     pub fn exec(&mut self, program: &Vec<Box<dyn Statement>>) -> Result<(), InterpreterError> {
         for stmt in program {
-            stmt.accept(self)?;
+            stmt.accept(self).map_err(|e| e.at(stmt.span()))?;
         }
 
         Ok(())
     }
 }
 
+/// Whether a `break` or `continue` with `label` applies to the loop
+/// labeled `loop_label`. Unlabeled ones apply to the innermost loop.
+fn targets(label: &Option<String>, loop_label: &Option<String>) -> bool {
+    label.is_none() || label == loop_label
+}
+
+/// Field `field` of `target`, with what it is called on when it is a
+/// property of an object.
+fn property(target: &ScriptValue, field: &str, span: Span) -> Result<(ScriptValue, Option<Receiver>), InterpreterError> {
+    match target {
+        ScriptValue::Object(obj) => match lookup(obj, field) {
+            Some((value, receiver)) => Ok((value, Some(receiver))),
+            None => Err(InterpreterError::new(target, InterpreterErrorType::PropertyNotFound).at(span))
+        },
+        ScriptValue::List(list) => match list::method(list, field) {
+            Some(method) => Ok((method, None)),
+            None => Err(InterpreterError::new(target, InterpreterErrorType::PropertyNotFound).at(span))
+        },
+        ScriptValue::Map(map) => match map::method(map, field) {
+            Some(method) => Ok((method, None)),
+            None => Err(InterpreterError::new(target, InterpreterErrorType::PropertyNotFound).at(span))
+        },
+        _ => Err(InterpreterError::new(target, InterpreterErrorType::NotObject).at(span))
+    }
+}
+
 impl StatementVisitor for Interpreter {
     fn visit_assignment(&mut self, stmt: &AssignmentStatement) -> StatementResult {
         let value = stmt.expr.accept(self)?;
 
-        stmt.assignee.assign(self, value);
+        stmt.assignee.assign(self, value)?;
 
         Ok(StatementValue::Normal(ScriptValue::Unit))
     }
@@ -42,7 +93,7 @@ impl StatementVisitor for Interpreter {
         };
 
         for var in &stmt.variables {
-            self.env.put_new(&var, value.clone());
+            self.env.put_new(var, value.clone());
         }
 
         Ok(StatementValue::Normal(ScriptValue::Unit))
@@ -51,16 +102,16 @@ impl StatementVisitor for Interpreter {
     fn visit_block(&mut self, stmt: &BlockStatement) -> StatementResult {
         self.env.enter();
         for stmt in &stmt.body {
-            let ret = stmt.accept(self)?;
+            let ret = stmt.accept(self).map_err(|e| e.at(stmt.span()))?;
             if let StatementValue::Normal(_) = ret {
                 continue;
             } else {
-                self.env.exit();
+                self.env.exit()?;
                 return Ok(ret);
             }
         }
 
-        self.env.exit();
+        self.env.exit()?;
 
         Ok(StatementValue::Normal(ScriptValue::Unit))
     }
@@ -68,11 +119,12 @@ impl StatementVisitor for Interpreter {
     fn visit_function(&mut self, stmt: &FunctionStatement) -> StatementResult {
         self.env.enter();
         let func = Function::new(
+            stmt.name.clone(),
             stmt.params.clone(),
             stmt.body.clone(),
             Rc::clone(&self.env.env),
         );
-        self.env.exit();
+        self.env.exit()?;
         self.env.put_new(&stmt.name, ScriptValue::Function(func));
 
         Ok(StatementValue::Normal(ScriptValue::Unit))
@@ -90,9 +142,45 @@ impl StatementVisitor for Interpreter {
 
     fn visit_while(&mut self, stmt: &WhileStatement) -> StatementResult {
         while let ScriptValue::Boolean(true) = stmt.condition.accept(self)? {
-            let res = stmt.body.accept(self)?;
-            if let StatementValue::Return(_) = res {
-                return Ok(res);
+            match stmt.body.accept(self)? {
+                StatementValue::Break(label) if targets(&label, &stmt.label) => break,
+                StatementValue::Continue(label) if targets(&label, &stmt.label) => continue,
+                StatementValue::Normal(_) => (),
+                res => return Ok(res),
+            }
+        }
+
+        Ok(StatementValue::Normal(ScriptValue::Unit))
+    }
+
+    fn visit_for(&mut self, stmt: &ForStatement) -> StatementResult {
+        let iterable = stmt.iterable.accept(self)?;
+        let mut iterator = ScriptIterator::new(self, &iterable, stmt.iterable.span()).map_err(|e| e.at(stmt.iterable.span()))?;
+
+        loop {
+            let values = match stmt.variables.len() {
+                2 => iterator.next(self).map(|entry| entry.map(|(key, value)| vec![key, value])),
+                _ => iterator.next_item(self).map(|item| item.map(|item| vec![item])),
+            };
+            let values = match values.map_err(|e| e.at(stmt.iterable.span()))? {
+                Some(values) => values,
+                None => break,
+            };
+
+            // A new scope for each iteration, so closures capture the
+            // variables of that iteration
+            self.env.enter();
+            for (var, value) in stmt.variables.iter().zip(values) {
+                self.env.put_new(var, value);
+            }
+            let res = stmt.body.accept(self);
+            self.env.exit()?;
+
+            match res? {
+                StatementValue::Break(label) if targets(&label, &stmt.label) => break,
+                StatementValue::Continue(label) if targets(&label, &stmt.label) => continue,
+                StatementValue::Normal(_) => (),
+                res => return Ok(res),
             }
         }
 
@@ -107,6 +195,82 @@ impl StatementVisitor for Interpreter {
         )
     }
 
+    fn visit_break(&mut self, stmt: &BreakStatement) -> StatementResult {
+        Ok(StatementValue::Break(stmt.label.clone()))
+    }
+
+    fn visit_continue(&mut self, stmt: &ContinueStatement) -> StatementResult {
+        Ok(StatementValue::Continue(stmt.label.clone()))
+    }
+
+    fn visit_throw(&mut self, stmt: &ThrowStatement) -> StatementResult {
+        let value = stmt.expr.accept(self)?;
+        Err(InterpreterError::new(&value, InterpreterErrorType::Thrown).at(stmt.span))
+    }
+
+    fn visit_try(&mut self, stmt: &TryStatement) -> StatementResult {
+        // An error skips the scope exits on its way up, so the scope is
+        // restored by hand
+        let env = self.env.clone();
+
+        let mut res = stmt.body.accept(self);
+        if let (Err(error), Some((variable, body))) = (&res, &stmt.catch) {
+            self.env = env.clone();
+            self.env.enter();
+            self.env.put_new(variable, error.value());
+            res = body.accept(self);
+            self.env = env.clone();
+        }
+
+        if let Some(finally) = &stmt.finally {
+            if res.is_err() {
+                self.env = env;
+            }
+            match finally.accept(self)? {
+                StatementValue::Normal(_) => (),
+                // Leaving the finally body overrides the try
+                finally_res => return Ok(finally_res),
+            }
+        }
+
+        res
+    }
+
+    fn visit_class(&mut self, stmt: &ClassStatement) -> StatementResult {
+        let superclass = match &stmt.superclass {
+            Some(expr) => match expr.accept(self)? {
+                ScriptValue::Class(class) => Some(class),
+                other => return Err(InterpreterError::new(&other, InterpreterErrorType::NotClass).at(expr.span())),
+            },
+            None => None,
+        };
+
+        self.env.enter();
+        let methods = stmt
+            .methods
+            .iter()
+            .map(|method| {
+                let func = Function::new(
+                    format!("{}.{}", stmt.name, method.name),
+                    method.params.clone(),
+                    method.body.clone(),
+                    Rc::clone(&self.env.env),
+                );
+                (method.name.clone(), func)
+            })
+            .collect();
+        self.env.exit()?;
+
+        let class = Class {
+            name: stmt.name.clone(),
+            superclass,
+            methods,
+        };
+        self.env.put_new(&stmt.name, ScriptValue::Class(Rc::new(class)));
+
+        Ok(StatementValue::Normal(ScriptValue::Unit))
+    }
+
     fn visit_expression(&mut self, stmt: &ExpressionStatement) -> StatementResult {
         Ok(StatementValue::Normal(stmt.expr.accept(self)?))
     }
@@ -120,13 +284,13 @@ impl ExpressionVisitor for Interpreter {
     fn visit_addition(&mut self, expr: &AdditionExpression) -> ExpressionResult {
         let left = expr.left.accept(self)?;
         let right = expr.right.accept(self)?;
-        left.numeric(right, expr.operator.clone())
+        left.numeric(right, expr.operator.clone()).map_err(|e| e.at(expr.span))
     }
 
     fn visit_multiplication(&mut self, expr: &MultiplicationExpression) -> ExpressionResult {
         let left = expr.left.accept(self)?;
         let right = expr.right.accept(self)?;
-        left.numeric(right, expr.operator.clone())
+        left.numeric(right, expr.operator.clone()).map_err(|e| e.at(expr.span))
     }
 
     fn visit_value(&mut self, expr: &ScriptValue) -> ExpressionResult {
@@ -137,43 +301,62 @@ impl ExpressionVisitor for Interpreter {
         match self.env.get(&expr.identifier) {
             Some(var) => Ok(var.clone()),
             //None => panic!("variable not found {}", &expr.identifier),
-            None => Err(InterpreterError::new(&ScriptValue::None, InterpreterErrorType::UndefinedVariable(expr.identifier.clone())))
+            None => Err(InterpreterError::new(&ScriptValue::None, InterpreterErrorType::UndefinedVariable(expr.identifier.clone())).at(expr.span))
         }
     }
 
     fn visit_condition(&mut self, expr: &ConditionExpression) -> ExpressionResult {
         let left = expr.left.accept(self)?;
         let right = expr.right.accept(self)?;
-        left.boolean(right, expr.operator.clone())
+        left.boolean(right, expr.operator.clone()).map_err(|e| e.at(expr.span))
+    }
+
+    fn visit_logical(&mut self, expr: &LogicalExpression) -> ExpressionResult {
+        let left = expr.left.accept(self)?;
+        let short_circuits = match (&left, &expr.operator.token_type) {
+            (ScriptValue::Boolean(value), TokenType::And) => !value,
+            (ScriptValue::Boolean(value), TokenType::Or) => *value,
+            _ => return Err(InterpreterError::new(&left, InterpreterErrorType::NotBoolean).at(expr.left.span())),
+        };
+
+        if short_circuits {
+            return Ok(left);
+        }
+
+        match expr.right.accept(self)? {
+            right @ ScriptValue::Boolean(_) => Ok(right),
+            right => Err(InterpreterError::new(&right, InterpreterErrorType::NotBoolean).at(expr.right.span())),
+        }
     }
 
     fn visit_function(&mut self, expr: &FunctionExpression) -> ExpressionResult {
-        let target = expr.expr.accept(self)?;
+        // Methods get `self` from the object they are called on
+        let (target, receiver) = match expr.expr.as_access() {
+            Some(access) => {
+                let object = access.expr.accept(self)?;
+                property(&object, &access.field, access.span)?
+            }
+            None => (expr.expr.accept(self)?, None),
+        };
         self.env.enter();
         let val = match target {
-            ScriptValue::Function(func) => {
-                let f = func.borrow();
-                //let mut wrapper = Environment { env: Rc::clone(&f.env) };
-                //wrapper.enter();
-                let ret = f.call(self, &expr.params)?;
-                // wrapper.exit();
-                ret
-            }
-            _ => return Err(InterpreterError::new(&target, InterpreterErrorType::NotCallable))
+            ScriptValue::Function(func) => func.borrow().call_method(self, receiver.as_ref(), &expr.params, expr.span)?,
+            ScriptValue::Class(class) => class.construct(self, &expr.params, expr.span)?,
+            _ => return Err(InterpreterError::new(&target, InterpreterErrorType::NotCallable).at(expr.span))
         };
-        self.env.exit();
+        self.env.exit()?;
         Ok(val)
     }
 
     fn visit_access(&mut self, expr: &AccessExpression) -> ExpressionResult {
         let target = expr.expr.accept(self)?;
-        match &target {
-            ScriptValue::Object(obj) => match obj.borrow().get(&expr.field) {
-                Some(val) => Ok(val),
-                //None => panic!("Object has no property {}", &expr.field),
-                None => Err(InterpreterError::new(&target, InterpreterErrorType::PropertyNotFound))
-            },
-            _ => Err(InterpreterError::new(&target, InterpreterErrorType::NotObject))
+        match property(&target, &expr.field, expr.span)? {
+            // A method taken without calling it keeps its object for when it
+            // is called on its own
+            (ScriptValue::Function(method), Some(receiver)) => {
+                Ok(ScriptValue::Function(method.borrow().bound(receiver)))
+            }
+            (value, _) => Ok(value),
         }
     }
 
@@ -182,14 +365,103 @@ impl ExpressionVisitor for Interpreter {
         match &target {
             ScriptValue::List(list) => {
                 let index = expr.index_expr.accept(self)?;
+                let list = list.borrow();
+                let position = list::position(&index, list.len()).map_err(|e| e.at(expr.index_expr.span()))?;
+                Ok(list[position].clone())
+            }
+            ScriptValue::Map(map) => {
+                let key = expr.index_expr.accept(self)?;
+                map::get(map, &key).map_err(|e| e.at(expr.index_expr.span()))
+            }
+            ScriptValue::Range(range) => {
+                let index = expr.index_expr.accept(self)?;
+                // Any whole number is a valid index until the range ends
+                let position = list::position(&index, usize::MAX).map_err(|e| e.at(expr.index_expr.span()))?;
+                match range.get(position) {
+                    Some(n) => Ok(ScriptValue::Number(n)),
+                    None => {
+                        let err = InterpreterError::new(&index, InterpreterErrorType::IndexOutOfBounds);
+                        Err(err.at(expr.index_expr.span()))
+                    }
+                }
+            }
+            _ => Err(InterpreterError::new(&target, InterpreterErrorType::NotIndexable).at(expr.span))
+        }
+    }
+
+    fn visit_interpolation(&mut self, expr: &InterpolationExpression) -> ExpressionResult {
+        let mut result = String::new();
+        for part in &expr.parts {
+            result.push_str(&part.accept(self)?.to_string());
+        }
+
+        Ok(ScriptValue::String(Rc::new(RefCell::new(result))))
+    }
 
-                match index {
-                    ScriptValue::Number(n) => Ok(list.borrow()[n as usize].clone()),
-                    // _ => panic!("Index has to be a number, not {:?}", index),
-                    _ => Err(InterpreterError::new(&target, InterpreterErrorType::InvalidIndex))
+    fn visit_list(&mut self, expr: &ListExpression) -> ExpressionResult {
+        let mut items = Vec::new();
+        for item in &expr.items {
+            items.push(item.accept(self)?);
+        }
+
+        Ok(ScriptValue::List(Rc::new(RefCell::new(items))))
+    }
+
+    fn visit_object(&mut self, expr: &ObjectExpression) -> ExpressionResult {
+        let obj: Rc<RefCell<dyn ObjectLike>> = Object::new();
+        for (key, value) in &expr.fields {
+            let value = value.accept(self)?;
+            obj.borrow_mut().set(key.clone(), value)?;
+        }
+
+        Ok(ScriptValue::Object(obj))
+    }
+
+    fn visit_map(&mut self, expr: &MapExpression) -> ExpressionResult {
+        let mut entries = HashMap::new();
+        for (key, value) in &expr.entries {
+            let key = Key::new(&key.accept(self)?).map_err(|e| e.at(key.span()))?;
+            entries.insert(key, value.accept(self)?);
+        }
+
+        Ok(ScriptValue::Map(Rc::new(RefCell::new(entries))))
+    }
+
+    fn visit_lambda(&mut self, expr: &LambdaExpression) -> ExpressionResult {
+        self.env.enter();
+        let func = Function::new(
+            "<lambda>".to_string(),
+            expr.params.clone(),
+            expr.body.clone(),
+            Rc::clone(&self.env.env),
+        );
+        self.env.exit()?;
+
+        Ok(ScriptValue::Function(func))
+    }
+
+    fn visit_range(&mut self, expr: &RangeExpression) -> ExpressionResult {
+        let mut bounds = Vec::new();
+        for bound in [Some(&expr.start), Some(&expr.end), expr.step.as_ref()].iter().flatten() {
+            match bound.accept(self)? {
+                ScriptValue::Number(n) if n.is_finite() => bounds.push(n),
+                other => {
+                    return Err(InterpreterError::new(&other, InterpreterErrorType::InvalidRangeBound).at(bound.span()))
                 }
             }
-            _ => Err(InterpreterError::new(&target, InterpreterErrorType::NotIndexable))
         }
+
+        let (start, end) = (bounds[0], bounds[1]);
+        let step = bounds.get(2).copied().unwrap_or(1.0);
+        if step == 0.0 {
+            return Err(InterpreterError::new(&ScriptValue::Number(step), InterpreterErrorType::ZeroStep).at(expr.span));
+        }
+
+        Ok(ScriptValue::Range(Rc::new(Range {
+            start,
+            end,
+            step,
+            inclusive: expr.inclusive,
+        })))
     }
 }
@@ -0,0 +1,163 @@
+use crate::{
+    expression::*,
+    interpreter::{errors::*, Interpreter},
+    map,
+    object::{lookup, ObjectLike},
+    token::Span,
+};
+
+use std::{cell::RefCell, fmt, rc::Rc, vec::IntoIter};
+
+/// Iteration over a script value, used by `for` loops and the built-in
+/// collection functions.
+///
+/// Lists, strings, ranges, maps and plain objects are iterated directly, and
+/// other objects through the protocol described on [`ObjectLike`].
+pub struct ScriptIterator {
+    source: Source,
+    index: usize,
+    // Where the protocol methods are called from
+    span: Span,
+}
+
+enum Source {
+    // Key and value pairs, and whether a single loop variable takes the key
+    Entries(IntoIter<(ScriptValue, ScriptValue)>, bool),
+    Range(Range),
+    Protocol(Rc<RefCell<dyn ObjectLike>>),
+}
+
+/// Numbers from `start` towards `end` by `step`, made by `start..end`,
+/// `start..=end` and `start..end step n`. The numbers are only computed as
+/// they are iterated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    /// Number at `index`, if the range reaches that far. Computed from the
+    /// start each time, so steps do not add up rounding errors.
+    pub fn get(&self, index: usize) -> Option<f64> {
+        let n = self.start + index as f64 * self.step;
+        let before_end = (self.step > 0.0 && n < self.end) || (self.step < 0.0 && n > self.end);
+        if before_end || (self.inclusive && n == self.end) {
+            Some(n)
+        } else {
+            None
+        }
+    }
+}
+
+// Shown as written, since listing the numbers could take forever
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, operator, self.end)?;
+        if self.step != 1.0 {
+            write!(f, " step {}", self.step)?;
+        }
+        Ok(())
+    }
+}
+
+impl ScriptIterator {
+    pub fn new(interpreter: &mut Interpreter, value: &ScriptValue, span: Span) -> Result<ScriptIterator, InterpreterError> {
+        let not_iterable = || InterpreterError::new(value, InterpreterErrorType::NotIterable);
+
+        let source = match value {
+            ScriptValue::List(list) => {
+                let entries: Vec<_> = list
+                    .borrow()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| (ScriptValue::Number(i as f64), e.clone()))
+                    .collect();
+                Source::Entries(entries.into_iter(), false)
+            }
+            ScriptValue::Range(range) => Source::Range(**range),
+            ScriptValue::Map(map) => Source::Entries(map::entries(map).into_iter(), true),
+            ScriptValue::String(string) => {
+                let entries: Vec<_> = string
+                    .borrow()
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| (ScriptValue::Number(i as f64), string_value(c.to_string())))
+                    .collect();
+                Source::Entries(entries.into_iter(), false)
+            }
+            ScriptValue::Object(obj) => {
+                let (iter, next) = (lookup(obj, "iter"), lookup(obj, "next"));
+
+                match (iter, next) {
+                    (Some((ScriptValue::Function(iter), receiver)), _) => match iter.borrow().call_method(interpreter, Some(&receiver), &[], span)? {
+                        ScriptValue::Object(iterator) => Source::Protocol(iterator),
+                        _ => return Err(not_iterable()),
+                    },
+                    (_, Some((ScriptValue::Function(_), _))) => Source::Protocol(Rc::clone(obj)),
+                    _ => {
+                        let obj = obj.borrow();
+                        let entries: Vec<_> = obj
+                            .keys()
+                            .into_iter()
+                            .map(|key| {
+                                let value = obj.get(&key).unwrap_or(ScriptValue::None);
+                                (string_value(key), value)
+                            })
+                            .collect();
+                        Source::Entries(entries.into_iter(), true)
+                    }
+                }
+            }
+            _ => return Err(not_iterable()),
+        };
+
+        Ok(ScriptIterator { source, index: 0, span })
+    }
+
+    /// Next key and value. Keys are positions, except field names for
+    /// plain objects and keys for maps.
+    pub fn next(&mut self, interpreter: &mut Interpreter) -> Result<Option<(ScriptValue, ScriptValue)>, InterpreterError> {
+        let entry = match &mut self.source {
+            Source::Entries(entries, _) => entries.next(),
+            Source::Range(range) => range
+                .get(self.index)
+                .map(|n| (ScriptValue::Number(self.index as f64), ScriptValue::Number(n))),
+            Source::Protocol(iterator) => {
+                let next = lookup(iterator, "next");
+                let value = match next {
+                    Some((ScriptValue::Function(next), receiver)) => {
+                        next.borrow().call_method(interpreter, Some(&receiver), &[], self.span)?
+                    }
+                    _ => {
+                        let iterator = ScriptValue::Object(Rc::clone(iterator));
+                        return Err(InterpreterError::new(&iterator, InterpreterErrorType::NotIterable));
+                    }
+                };
+
+                match value {
+                    ScriptValue::Done => None,
+                    value => Some((ScriptValue::Number(self.index as f64), value)),
+                }
+            }
+        };
+
+        self.index += 1;
+        Ok(entry)
+    }
+
+    /// Next item for a single loop variable: the key for maps and plain
+    /// objects and the value otherwise.
+    pub fn next_item(&mut self, interpreter: &mut Interpreter) -> Result<Option<ScriptValue>, InterpreterError> {
+        let keyed = matches!(self.source, Source::Entries(_, true));
+        let entry = self.next(interpreter)?;
+        Ok(entry.map(|(key, value)| if keyed { key } else { value }))
+    }
+}
+
+fn string_value(string: String) -> ScriptValue {
+    ScriptValue::String(Rc::new(RefCell::new(string)))
+}
@@ -0,0 +1,115 @@
+use crate::{
+    expression::*, function::*, interpreter::*,
+    interpreter::errors::{InterpreterError, InterpreterErrorType}, statement::*,
+};
+
+use std::{cell::RefCell, rc::Rc};
+
+type List = Rc<RefCell<Vec<ScriptValue>>>;
+
+/// Looks up a built-in list method, bound to `list` as `self`.
+pub fn method(list: &List, name: &str) -> Option<ScriptValue> {
+    let (params, func): (Vec<&str>, InternalFunction) = match name {
+        "push" => (vec!["value"], |inpr| {
+            let value = arg(inpr, "value");
+            this(inpr)?.borrow_mut().push(value);
+            unit()
+        }),
+        "pop" => (Vec::new(), |inpr| {
+            let last = this(inpr)?.borrow_mut().pop();
+            Ok(StatementValue::Normal(last.unwrap_or(ScriptValue::None)))
+        }),
+        "len" => (Vec::new(), |inpr| {
+            let len = this(inpr)?.borrow().len();
+            Ok(StatementValue::Normal(ScriptValue::Number(len as f64)))
+        }),
+        "insert" => (vec!["index", "value"], |inpr| {
+            let list = this(inpr)?;
+            let len = list.borrow().len();
+            let index = index(inpr, "index", len + 1)?;
+            list.borrow_mut().insert(index, arg(inpr, "value"));
+            unit()
+        }),
+        "remove" => (vec!["index"], |inpr| {
+            let list = this(inpr)?;
+            let len = list.borrow().len();
+            let index = index(inpr, "index", len)?;
+            let removed = list.borrow_mut().remove(index);
+            Ok(StatementValue::Normal(removed))
+        }),
+        "slice" => (vec!["start", "end"], |inpr| {
+            let list = this(inpr)?;
+            let len = list.borrow().len();
+            let start = number(inpr, "start")?.max(0.0).min(len as f64) as usize;
+            let end = number(inpr, "end")?.max(start as f64).min(len as f64) as usize;
+            let slice = list.borrow()[start..end].to_vec();
+            Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(slice)))))
+        }),
+        "contains" => (vec!["value"], |inpr| {
+            let value = arg(inpr, "value");
+            let found = this(inpr)?.borrow().iter().any(|e| e.equals(&value));
+            Ok(StatementValue::Normal(ScriptValue::Boolean(found)))
+        }),
+        "index_of" => (vec!["value"], |inpr| {
+            let value = arg(inpr, "value");
+            let position = this(inpr)?.borrow().iter().position(|e| e.equals(&value));
+            let index = position.map_or(-1.0, |i| i as f64);
+            Ok(StatementValue::Normal(ScriptValue::Number(index)))
+        }),
+        "reverse" => (Vec::new(), |inpr| {
+            this(inpr)?.borrow_mut().reverse();
+            unit()
+        }),
+        "join" => (vec!["separator"], |inpr| {
+            let separator = arg(inpr, "separator").to_string();
+            let joined = this(inpr)?
+                .borrow()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(&separator);
+            Ok(StatementValue::Normal(ScriptValue::String(Rc::new(RefCell::new(joined)))))
+        }),
+        _ => return None,
+    };
+
+    Some(internal_method(name, params, func, ScriptValue::List(Rc::clone(list))))
+}
+
+fn this(inpr: &Interpreter) -> Result<List, InterpreterError> {
+    match arg(inpr, "self") {
+        ScriptValue::List(list) => Ok(list),
+        other => Err(InterpreterError::new(&other, InterpreterErrorType::NotList)),
+    }
+}
+
+fn number(inpr: &Interpreter, name: &str) -> Result<f64, InterpreterError> {
+    match arg(inpr, name) {
+        ScriptValue::Number(n) => Ok(n),
+        other => Err(InterpreterError::new(&other, InterpreterErrorType::NotNumber)),
+    }
+}
+
+/// Argument `name` as an index below `bound`.
+fn index(inpr: &Interpreter, name: &str, bound: usize) -> Result<usize, InterpreterError> {
+    position(&arg(inpr, name), bound)
+}
+
+/// `index` as a position in a list of length `bound`. Indexes have to be
+/// whole numbers, and negative ones are out of bounds.
+pub fn position(index: &ScriptValue, bound: usize) -> Result<usize, InterpreterError> {
+    match index {
+        ScriptValue::Number(n) if n.fract() != 0.0 || n.is_nan() => {
+            Err(InterpreterError::new(index, InterpreterErrorType::InvalidIndex))
+        }
+        ScriptValue::Number(n) if *n < 0.0 || *n as usize >= bound => {
+            Err(InterpreterError::new(index, InterpreterErrorType::IndexOutOfBounds))
+        }
+        ScriptValue::Number(n) => Ok(*n as usize),
+        _ => Err(InterpreterError::new(index, InterpreterErrorType::InvalidIndex)),
+    }
+}
+
+fn unit() -> StatementResult {
+    Ok(StatementValue::Normal(ScriptValue::Unit))
+}
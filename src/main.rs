@@ -1,10 +1,15 @@
 mod builtin;
+mod class;
 mod environment;
 mod expression;
 mod function;
 mod interpreter;
+mod iterator;
+mod list;
+mod map;
 mod object;
 mod parser;
+mod repl;
 mod scanner;
 mod statement;
 mod token;
@@ -14,36 +19,73 @@ use environment::Environment;
 use interpreter::Interpreter;
 use parser::Parser;
 use scanner::Scanner;
+use token::Span;
 
 use std::env;
+use std::fmt::Display;
 use std::fs;
 use std::process;
 
+pub fn report(error: &dyn Display, span: Option<Span>, source: &str) {
+    println!("{}", error);
+    if let Some(span) = span {
+        print!("{}", span.underline(source));
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let file_name = &args[1];
-
-    let input = fs::read_to_string(file_name).unwrap();
-    let scanner = Scanner::new(input);
-    let tokens = scanner.scan();
+    // `--tokens` prints the tokens of the file, comments included
+    let tokens_only = args.get(1).is_some_and(|arg| arg == "--tokens");
+    let file_name = match args.get(if tokens_only { 2 } else { 1 }) {
+        Some(file_name) => file_name,
+        None => return repl::run(),
+    };
 
-    let mut parser = Parser::new(tokens);
-    let program = match parser.parse() {
-        Ok(result) => result,
+    let input = match fs::read_to_string(file_name) {
+        Ok(input) => input,
+        Err(e) => {
+            println!("Cannot read {}: {}", file_name, e);
+            process::exit(1);
+        }
+    };
+    let scanner = Scanner::new(input.clone());
+    let scanner = if tokens_only { scanner.with_comments() } else { scanner };
+    let tokens = match scanner.scan() {
+        Ok(tokens) => tokens,
         Err(e) => {
-            println!("{}", e);
+            report(&e, Some(e.span), &input);
             process::exit(1);
         }
     };
 
+    if tokens_only {
+        for token in &tokens {
+            println!("{}:{} {:?}", token.span.line, token.span.col, token.token_type);
+        }
+        return;
+    }
+
+    let mut parser = Parser::new(tokens);
+    let (program, errors) = parser.parse();
+    if !errors.is_empty() {
+        for e in &errors {
+            report(e, e.span(), &input);
+        }
+        process::exit(1);
+    }
+
     let mut env = Environment::new();
     create_builtins(&mut env);
 
-    let mut interpreter = Interpreter { env };
-    let result = interpreter.exec(program);
+    let mut interpreter = Interpreter::new(env);
+    let result = interpreter.exec(&program);
 
     if let Err(error) = result {
-        println!("{}", error);
+        if let Some(traceback) = error.traceback() {
+            print!("{}", traceback);
+        }
+        report(&error, error.span, &input);
         process::exit(1);
     }
 }
@@ -0,0 +1,166 @@
+use crate::{
+    expression::*, function::*, interpreter::*,
+    interpreter::errors::{InterpreterError, InterpreterErrorType}, statement::*,
+};
+
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+pub type Map = Rc<RefCell<HashMap<Key, ScriptValue>>>;
+
+/// A map key, made from a number, string, boolean or `null`.
+///
+/// Number keys are equal when `==` says so, except that `NaN` is equal to
+/// itself as a key, so it can be looked up again. `0` and `-0` are the same
+/// key.
+#[derive(Debug, Clone)]
+pub enum Key {
+    None,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Key {
+    /// `value` as a key, with `-0` and all `NaN`s turned into one of each.
+    pub fn new(value: &ScriptValue) -> Result<Key, InterpreterError> {
+        match value {
+            ScriptValue::None => Ok(Key::None),
+            ScriptValue::Boolean(b) => Ok(Key::Boolean(*b)),
+            ScriptValue::Number(n) if n.is_nan() => Ok(Key::Number(f64::NAN)),
+            ScriptValue::Number(n) if *n == 0.0 => Ok(Key::Number(0.0)),
+            ScriptValue::Number(n) => Ok(Key::Number(*n)),
+            ScriptValue::String(s) => Ok(Key::String(s.borrow().clone())),
+            _ => Err(InterpreterError::new(value, InterpreterErrorType::NotHashable)),
+        }
+    }
+
+    pub fn value(&self) -> ScriptValue {
+        match self {
+            Key::None => ScriptValue::None,
+            Key::Boolean(b) => ScriptValue::Boolean(*b),
+            Key::Number(n) => ScriptValue::Number(*n),
+            Key::String(s) => ScriptValue::String(Rc::new(RefCell::new(s.clone()))),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Key::None => 0,
+            Key::Boolean(_) => 1,
+            Key::Number(_) => 2,
+            Key::String(_) => 3,
+        }
+    }
+}
+
+// Keys are normalized on creation, so numbers can compare by their bits
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Key::None => (),
+            Key::Boolean(b) => b.hash(state),
+            Key::Number(n) => n.to_bits().hash(state),
+            Key::String(s) => s.hash(state),
+        }
+    }
+}
+
+/// Order in which maps list their keys: `null`, booleans, numbers and then
+/// strings, each in ascending order, with `NaN` after all other numbers.
+impl Ord for Key {
+    fn cmp(&self, other: &Key) -> Ordering {
+        match (self, other) {
+            (Key::Boolean(left), Key::Boolean(right)) => left.cmp(right),
+            (Key::Number(left), Key::Number(right)) => left.total_cmp(right),
+            (Key::String(left), Key::String(right)) => left.cmp(right),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Key) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Entries of `map`, sorted by key so the order does not depend on the
+/// hash order.
+pub fn entries(map: &Map) -> Vec<(ScriptValue, ScriptValue)> {
+    let map = map.borrow();
+    let mut keys: Vec<&Key> = map.keys().collect();
+    keys.sort();
+    keys.into_iter().map(|key| (key.value(), map[key].clone())).collect()
+}
+
+/// Value of `key` in `map`.
+pub fn get(map: &Map, key: &ScriptValue) -> Result<ScriptValue, InterpreterError> {
+    match map.borrow().get(&Key::new(key)?) {
+        Some(value) => Ok(value.clone()),
+        None => Err(InterpreterError::new(key, InterpreterErrorType::KeyNotFound)),
+    }
+}
+
+/// Looks up a built-in map method, bound to `map` as `self`.
+pub fn method(map: &Map, name: &str) -> Option<ScriptValue> {
+    let (params, func): (Vec<&str>, InternalFunction) = match name {
+        "keys" => (Vec::new(), |inpr| {
+            let keys = entries(&this(inpr)?).into_iter().map(|(key, _)| key).collect();
+            list(keys)
+        }),
+        "values" => (Vec::new(), |inpr| {
+            let values = entries(&this(inpr)?).into_iter().map(|(_, value)| value).collect();
+            list(values)
+        }),
+        "entries" => (Vec::new(), |inpr| {
+            let entries = entries(&this(inpr)?)
+                .into_iter()
+                .map(|(key, value)| ScriptValue::List(Rc::new(RefCell::new(vec![key, value]))))
+                .collect();
+            list(entries)
+        }),
+        "has" => (vec!["key"], |inpr| {
+            let key = Key::new(&arg(inpr, "key"))?;
+            let found = this(inpr)?.borrow().contains_key(&key);
+            Ok(StatementValue::Normal(ScriptValue::Boolean(found)))
+        }),
+        "delete" => (vec!["key"], |inpr| {
+            let key = Key::new(&arg(inpr, "key"))?;
+            let removed = this(inpr)?.borrow_mut().remove(&key);
+            Ok(StatementValue::Normal(ScriptValue::Boolean(removed.is_some())))
+        }),
+        "len" => (Vec::new(), |inpr| {
+            let len = this(inpr)?.borrow().len();
+            Ok(StatementValue::Normal(ScriptValue::Number(len as f64)))
+        }),
+        _ => return None,
+    };
+
+    Some(internal_method(name, params, func, ScriptValue::Map(Rc::clone(map))))
+}
+
+fn this(inpr: &Interpreter) -> Result<Map, InterpreterError> {
+    match arg(inpr, "self") {
+        ScriptValue::Map(map) => Ok(map),
+        other => Err(InterpreterError::new(&other, InterpreterErrorType::NotMap)),
+    }
+}
+
+fn list(items: Vec<ScriptValue>) -> StatementResult {
+    Ok(StatementValue::Normal(ScriptValue::List(Rc::new(RefCell::new(items)))))
+}
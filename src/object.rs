@@ -1,76 +1,107 @@
+use crate::class::{Class, Receiver};
 use crate::expression::*;
-use crate::function::*;
+use crate::interpreter::errors::{InterpreterError, InterpreterErrorType};
 
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter, Result};
 use std::rc::Rc;
 
+/// A value with fields, either a script object or a Rust-backed host type.
+///
+/// Objects can be made iterable for `for` loops and `map` with an `iter`
+/// field holding a function that returns an iterator object. An iterator
+/// has a `next` function returning the next value, or `done` when there are
+/// no more. An object with `next` but no `iter` is its own iterator.
 pub trait ObjectLike: Debug + Display {
     fn get(&self, key: &str) -> Option<ScriptValue>;
-    fn set(&mut self, key: String, val: ScriptValue);
+    fn set(&mut self, key: String, val: ScriptValue) -> std::result::Result<(), InterpreterError>;
+    fn keys(&self) -> Vec<String>;
+
+    /// Class whose methods are looked up for missing fields.
+    fn class(&self) -> Option<Rc<Class>> {
+        None
+    }
+
+    /// What `self` is in methods looked up on the object, if not the
+    /// object itself.
+    fn receiver(&self) -> Option<ScriptValue> {
+        None
+    }
+}
+
+/// Field `key` of `obj`, or else the method of its class, along with what
+/// it is called on. Methods are shared by all instances and only get their
+/// `self` when called.
+pub fn lookup(obj: &Rc<RefCell<dyn ObjectLike>>, key: &str) -> Option<(ScriptValue, Receiver)> {
+    let obj_ref = obj.borrow();
+    let this = obj_ref.receiver().unwrap_or_else(|| ScriptValue::Object(Rc::clone(obj)));
+
+    if let Some(field) = obj_ref.get(key) {
+        return Some((field, Receiver { this, class: None }));
+    }
+
+    let (method, class) = obj_ref.class()?.find_method(key)?;
+    Some((ScriptValue::Function(method), Receiver { this, class: Some(class) }))
 }
 
 #[derive(Debug)]
 pub struct Object {
     pub fields: HashMap<String, ScriptValue>,
+    pub class: Option<Rc<Class>>,
 }
 
 impl Object {
     pub fn new() -> Rc<RefCell<Object>> {
         Rc::new(RefCell::new(Object {
             fields: HashMap::new(),
+            class: None,
         }))
     }
 
-    pub fn set_ref(obj: Rc<RefCell<dyn ObjectLike>>, key: String, value: ScriptValue) {
-        let mut obj_ref = obj.borrow_mut();
-        match value {
-            ScriptValue::Function(func) => {
-                let mut borrowed = func.borrow_mut();
-
-                borrowed.env.enter();
-                borrowed
-                    .env
-                    .put_new("self", ScriptValue::Object(obj.clone()));
-
-                let method = Function::new(
-                    borrowed.params.clone(),
-                    borrowed.body.clone(),
-                    Rc::clone(&borrowed.env.env),
-                );
-
-                borrowed.env.exit();
-                obj_ref.set(key, ScriptValue::Function(method));
-            }
-            _ => obj_ref.set(key, value),
-        }
+    pub fn instance(class: Rc<Class>) -> Rc<RefCell<Object>> {
+        Rc::new(RefCell::new(Object {
+            fields: HashMap::new(),
+            class: Some(class),
+        }))
     }
 }
 
 impl ObjectLike for Object {
-    fn set(&mut self, key: String, value: ScriptValue) {
+    fn set(&mut self, key: String, value: ScriptValue) -> std::result::Result<(), InterpreterError> {
         self.fields.insert(key, value);
+        Ok(())
     }
 
     fn get(&self, key: &str) -> Option<ScriptValue> {
-        match self.fields.get(key) {
-            Some(val) => Some(val.clone()),
-            None => None,
-        }
+        self.fields.get(key).cloned()
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.fields.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    fn class(&self) -> Option<Rc<Class>> {
+        self.class.clone()
     }
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(class) = &self.class {
+            write!(f, "{} ", class.name)?;
+        }
         write!(f, "{{ ")?;
 
         write!(
             f,
             "{}",
-            self.fields
+            // Sorted, so the output does not depend on the hash order
+            self.keys()
                 .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
+                .map(|k| format!("{}: {}", k, self.fields[k]))
                 .collect::<Vec<_>>()
                 .join(", ")
         )?;
@@ -80,8 +111,9 @@ impl Display for Object {
 }
 
 impl ObjectLike for String {
-    fn set(&mut self, _: String, _: ScriptValue) {
-        panic!("Cannot set field for immutable string");
+    fn set(&mut self, _: String, _: ScriptValue) -> std::result::Result<(), InterpreterError> {
+        let string = ScriptValue::String(Rc::new(RefCell::new(self.clone())));
+        Err(InterpreterError::new(&string, InterpreterErrorType::ReadOnly))
     }
 
     fn get(&self, key: &str) -> Option<ScriptValue> {
@@ -90,7 +122,11 @@ impl ObjectLike for String {
                 // Todo easy way to return functions?
                 Some(ScriptValue::Number(self.len() as f64))
             }
-            _ => panic!("String has no property {}", key),
+            _ => None,
         }
     }
+
+    fn keys(&self) -> Vec<String> {
+        vec!["length".to_string()]
+    }
 }
@@ -1,11 +1,15 @@
 use std::fmt;
-use crate::token::{Token,TokenType};
+use crate::token::{Span,Token,TokenType};
 
 
 #[derive(Debug,Clone)]
 pub enum ParserErrorType {
-    EOF,
+    Eof,
     Unexpected(String),
+    OutsideLoop(String),
+    UndefinedLabel(String),
+    InvalidAssignmentTarget,
+    IncompleteInterpolation,
 }
 // TODO actual info to parserError
 #[derive(Debug, Clone)]
@@ -29,10 +33,51 @@ impl ParserError {
         }
     }
 
+    pub fn outside_loop(token: &Token, keyword: &str) -> ParserError {
+        ParserError {
+            token: Some(token.clone()),
+            err_type: ParserErrorType::OutsideLoop(keyword.to_string())
+        }
+    }
+
+    pub fn undefined_label(token: &Token, label: &str) -> ParserError {
+        ParserError {
+            token: Some(token.clone()),
+            err_type: ParserErrorType::UndefinedLabel(label.to_string())
+        }
+    }
+
+    /// Error for assigning with `token` to the expression at `target`.
+    pub fn invalid_assignment(token: &Token, target: Span) -> ParserError {
+        ParserError {
+            token: Some(Token { span: target, ..token.clone() }),
+            err_type: ParserErrorType::InvalidAssignmentTarget
+        }
+    }
+
+    /// Error for an empty or unfinished `${...}` in the string `token`.
+    pub fn incomplete_interpolation(token: &Token) -> ParserError {
+        ParserError {
+            token: Some(token.clone()),
+            err_type: ParserErrorType::IncompleteInterpolation
+        }
+    }
+
     pub fn eof() -> ParserError {
         ParserError {
             token: None,
-            err_type: ParserErrorType::EOF
+            err_type: ParserErrorType::Eof
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.token.as_ref().map(|token| token.span)
+    }
+
+    fn write_location(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(f, "Line {}, col {}: ", span.line, span.col),
+            None => Ok(()),
         }
     }
 }
@@ -40,18 +85,34 @@ impl ParserError {
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.err_type {
-            ParserErrorType::EOF => {
+            ParserErrorType::Eof => {
                 write!(f, "Reached end of line while parsing")
             },
             ParserErrorType::Unexpected(expected) => {
                 let err_string = if let Some(token) = self.token.clone() {
-                    format!("Line {}: expected {}, found {:?}", token.line, expected, token.token_type)
+                    format!("Line {}, col {}: expected {}, found {:?}", token.span.line, token.span.col, expected, token.token_type)
                 } else {
                     format!("Expected {}, reached EOF", expected)
                 };
 
                 write!(f, "{}", err_string)
             }
+            ParserErrorType::OutsideLoop(keyword) => {
+                self.write_location(f)?;
+                write!(f, "{} outside of a loop", keyword)
+            }
+            ParserErrorType::UndefinedLabel(label) => {
+                self.write_location(f)?;
+                write!(f, "no enclosing loop labeled {}", label)
+            }
+            ParserErrorType::InvalidAssignmentTarget => {
+                self.write_location(f)?;
+                write!(f, "invalid assignment target")
+            }
+            ParserErrorType::IncompleteInterpolation => {
+                self.write_location(f)?;
+                write!(f, "incomplete expression in interpolation")
+            }
         }
     }
 }
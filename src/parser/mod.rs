@@ -1,4 +1,5 @@
 use crate::expression::*;
+use crate::function::{Argument, Parameter};
 use crate::statement::*;
 use crate::token::*;
 use errors::*;
@@ -8,43 +9,165 @@ use std::rc::Rc;
 
 pub mod errors;
 
-type Program = Vec<Box<dyn Statement>>;
+pub type Program = Vec<Box<dyn Statement>>;
 
 
 type ExpressionResult = Result<Box<dyn Expression>, ParserError>;
 
+const OR: u8 = 1;
+const AND: u8 = 2;
+const EQUALITY: u8 = 3;
+const COMPARISON: u8 = 4;
+const RANGE: u8 = 5;
+const ADDITIVE: u8 = 6;
+const MULTIPLICATIVE: u8 = 7;
+
+/// Binding power of binary operators, higher binds tighter.
+fn precedence(token_type: &TokenType) -> Option<u8> {
+    match token_type {
+        TokenType::Or => Some(OR),
+        TokenType::And => Some(AND),
+        TokenType::Equals | TokenType::NotEquals => Some(EQUALITY),
+        TokenType::Greater | TokenType::Lesser | TokenType::EqGreater | TokenType::EqLesser => {
+            Some(COMPARISON)
+        }
+        TokenType::DotDot | TokenType::DotDotEq => Some(RANGE),
+        TokenType::Plus | TokenType::Minus => Some(ADDITIVE),
+        TokenType::Star | TokenType::Slash => Some(MULTIPLICATIVE),
+        _ => None,
+    }
+}
+
 
 pub struct Parser {
     //input: MultiPeek<IntoIter<&'a Token>>,
     input: Tokens,
-    program: Program,
+    errors: Vec<ParserError>,
+    // Labels of the loops enclosing the current statement
+    loops: Vec<Option<String>>,
+    // Number of blocks enclosing the current statement
+    blocks: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
         Parser {
             input: Tokens::new(tokens),
-            program: Vec::new(),
+            errors: Vec::new(),
+            loops: Vec::new(),
+            blocks: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Result<&mut Program, ParserError> {
-        loop {
-            let stmt = self.statement()?;
-            self.program.push(stmt);
-            if let None = self.current() {
-                break;
+    /// Parses the whole input, recovering from errors at statement
+    /// boundaries. Returns the statements that parsed and every error found.
+    pub fn parse(&mut self) -> (Program, Vec<ParserError>) {
+        let mut program = Vec::new();
+        while self.current().is_some() {
+            if let Some(stmt) = self.recovering_statement() {
+                program.push(stmt);
+            }
+        }
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    fn recovering_statement(&mut self) -> Option<Box<dyn Statement>> {
+        let start = self.input.position();
+        match self.statement() {
+            Ok(stmt) => Some(stmt),
+            Err(error) => {
+                // The offending token may start the next statement, so
+                // synchronize from it rather than after it
+                if error.span().is_some() && error.span() == self.input.previous().map(|t| t.span) {
+                    self.input.step_back();
+                }
+                self.errors.push(error);
+                if self.input.position() <= start {
+                    self.input.skip_to(start + 1);
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    /// Skips tokens until one that starts or ends a statement. Blocks opened
+    /// while skipping are skipped as a whole, and a `}` outside of any block
+    /// is skipped too, as nothing would end on it.
+    fn synchronize(&mut self) {
+        let in_block = self.blocks > 0;
+        let mut depth = 0;
+        while let Some(token) = self.current() {
+            match token.token_type {
+                TokenType::Var
+                | TokenType::Func
+                | TokenType::If
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Class
+                    if depth == 0 =>
+                {
+                    return
+                }
+                TokenType::RightBracket if depth == 0 && in_block => return,
+                TokenType::LeftBracket => depth += 1,
+                TokenType::RightBracket if depth > 0 => depth -= 1,
+                _ => (),
             }
+            self.consume();
         }
-        Ok(&mut self.program)
     }
 
     fn current(&mut self) -> Option<&Token> {
-        return self.input.current();
+        self.input.current()
+    }
+
+    fn lookahead(&self, amount: usize) -> Option<TokenType> {
+        self.input.peek(amount).unwrap_type()
+    }
+
+    /// Whether the tokens `amount` ahead are a `key:` or `[key]:` that can
+    /// only start an object or map literal, telling it apart from a block.
+    fn object_key_ahead(&self, amount: usize) -> bool {
+        if self.lookahead(amount) == Some(TokenType::LeftBrace) {
+            return self.computed_key_ahead(amount);
+        }
+
+        let labeled_loop = matches!(
+            self.lookahead(amount + 2),
+            Some(TokenType::While) | Some(TokenType::For)
+        );
+        !labeled_loop
+            && matches!(
+                (self.lookahead(amount), self.lookahead(amount + 1)),
+                (
+                    Some(TokenType::Identifier(_)) | Some(TokenType::String(_)),
+                    Some(TokenType::Colon)
+                )
+            )
     }
 
-    fn _lookahead(&mut self) -> Option<&Token> {
-        return self.input._peek();
+    /// Whether the `[` `amount` ahead is closed by a `]` followed by `:`.
+    fn computed_key_ahead(&self, mut amount: usize) -> bool {
+        // The key can contain lists and indexing of its own
+        let mut depth = 0;
+        loop {
+            amount += 1;
+            match self.lookahead(amount) {
+                Some(TokenType::LeftBrace) => depth += 1,
+                Some(TokenType::RightBrace) if depth == 0 => {
+                    return self.lookahead(amount + 1) == Some(TokenType::Colon)
+                }
+                Some(TokenType::RightBrace) => depth -= 1,
+                Some(_) => (),
+                None => return false,
+            }
+        }
     }
 
     fn advance(&mut self) -> Option<&Token> {
@@ -59,6 +182,15 @@ impl Parser {
         self.input._skip(amount)
     }
 
+    fn previous_span(&self) -> Span {
+        self.input.previous().map(|token| token.span).unwrap_or_default()
+    }
+
+    /// Span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous_span())
+    }
+
     fn statement(&mut self) -> Result<Box<dyn Statement>, ParserError> {
        let current = self.current().ok_or(ParserError::eof())?.clone();
 
@@ -75,24 +207,20 @@ impl Parser {
                     Box::new(DeclarationStatement {
                         variables: vec![identifier],
                         initializer: Some(expr),
+                        span: self.span_from(current.span),
                     })
                 } else {
-                    return Err(ParserError::unexpected(&current, "identifier"));
+                    return Err(ParserError::unexpected(var, "identifier"));
                 }
             }
-            TokenType::Identifier(_) => {
-                let expr = self.expression()?;
-                if let Some(TokenType::Assign) = self.current().unwrap_type() {
-                    self.consume();
-                    let value = self.expression()?;
-                    Box::new(AssignmentStatement {
-                        assignee: expr,
-                        expr: value,
-                    })
-
-                    // self.consume();
-                } else {
-                    Box::new(ExpressionStatement { expr })
+            TokenType::Identifier(label) if self.lookahead(1) == Some(TokenType::Colon) => {
+                self.advance();
+                match self.advance().unwrap_type() {
+                    Some(TokenType::For) => self.for_loop(Some(label), current.span)?,
+                    _ => {
+                        self.current().should_be(TokenType::While)?;
+                        self.while_loop(Some(label), current.span)?
+                    }
                 }
             }
             TokenType::If => {
@@ -111,38 +239,56 @@ impl Parser {
                     condition,
                     if_body,
                     else_body,
+                    span: self.span_from(current.span),
                 })
             }
-            TokenType::While => {
+            TokenType::While => self.while_loop(None, current.span)?,
+            TokenType::For => self.for_loop(None, current.span)?,
+            TokenType::Break | TokenType::Continue => {
+                let label = match self.lookahead(1) {
+                    // Statements are not terminated, so a label has to be on the same line
+                    Some(TokenType::Identifier(label))
+                        if self.input.peek(1).map(|t| t.span.line) == Some(current.span.line) =>
+                    {
+                        self.advance();
+                        Some(label)
+                    }
+                    _ => None,
+                };
                 self.advance();
-                let condition = self.expression()?;
-                let body = self.statement()?;
 
-                Box::new(WhileStatement { condition, body })
+                let keyword = if current.token_type == TokenType::Break { "break" } else { "continue" };
+                if self.loops.is_empty() {
+                    return Err(ParserError::outside_loop(&current, keyword));
+                }
+                if let Some(label) = &label {
+                    if !self.loops.contains(&Some(label.clone())) {
+                        return Err(ParserError::undefined_label(&current, label));
+                    }
+                }
+
+                let span = self.span_from(current.span);
+                if current.token_type == TokenType::Break {
+                    Box::new(BreakStatement { label, span })
+                } else {
+                    Box::new(ContinueStatement { label, span })
+                }
             }
-            TokenType::Func => {
+            // Without a name this is a function expression
+            TokenType::Func if matches!(self.lookahead(1), Some(TokenType::Identifier(_))) => {
                 let next = self.advance();
                 if let Some(TokenType::Identifier(ident)) = next.unwrap_type() {
                     let name = ident.to_owned();
 
-                    self.advance().should_be(TokenType::LeftParen)?;
                     self.advance();
-                    let mut params = Vec::new();
-                    while let Some(TokenType::Identifier(ident)) = self.current().unwrap_type() {
-                        params.push(ident.clone());
-                        if let Some(TokenType::Comma) = self.advance().unwrap_type() {
-                            self.consume();
-                        } else {
-                            break;
-                        }
-                    }
-                    self.consume().should_be(TokenType::RightParen)?;
-                    let body = self.statement()?;
+                    let params = self.parameters()?;
+                    let body = self.function_body()?;
 
                     Box::new(FunctionStatement {
                         name,
                         params,
                         body: Rc::from(body),
+                        span: self.span_from(current.span),
                     })
                 } else {
                     return Err(ParserError::unexpected(&current, "identifier"));
@@ -151,195 +297,643 @@ impl Parser {
             TokenType::Return => {
                 self.consume();
                 let expr = self.expression()?;
-                Box::new(ReturnStatement { expr })
+                Box::new(ReturnStatement {
+                    expr,
+                    span: self.span_from(current.span),
+                })
             }
-            TokenType::LeftBracket => {
+            TokenType::Throw => {
+                self.advance();
+                let expr = self.expression()?;
+                Box::new(ThrowStatement {
+                    expr,
+                    span: self.span_from(current.span),
+                })
+            }
+            TokenType::Try => self.try_statement(current.span)?,
+            TokenType::Class => self.class(current.span)?,
+            TokenType::LeftBracket if !self.object_key_ahead(1) => {
                 self.advance();
                 let mut body = Vec::new();
+                self.blocks += 1;
                 while let Some(token) = self.current() {
                     if token.token_type == TokenType::RightBracket {
                         self.advance();
                         break;
                     }
-                    let stmt = self.statement()?;
-                    body.push(stmt);
+                    if let Some(stmt) = self.recovering_statement() {
+                        body.push(stmt);
+                    }
                 }
+                self.blocks -= 1;
 
-                Box::new(BlockStatement { body })
+                Box::new(BlockStatement {
+                    body,
+                    span: self.span_from(current.span),
+                })
+            }
+            _ => {
+                let expr = self.expression()?;
+                if let Some(TokenType::Assign) = self.current().unwrap_type() {
+                    let assign = self.consume().ok_or(ParserError::eof())?.clone();
+                    let value = self.expression()?;
+                    // The statement parsed fine, so there is nothing to recover from
+                    if !expr.is_assignable() {
+                        self.errors.push(ParserError::invalid_assignment(&assign, expr.span()));
+                    }
+
+                    Box::new(AssignmentStatement {
+                        assignee: expr,
+                        expr: value,
+                        span: self.span_from(current.span),
+                    })
+                } else {
+                    Box::new(ExpressionStatement {
+                        expr,
+                        span: self.span_from(current.span),
+                    })
+                }
             }
-            _ => Box::new(ExpressionStatement {
-                expr: self.expression()?,
-            }),
         };
 
         Ok(stmt)
     }
 
     fn expression(&mut self) -> ExpressionResult {
-        self.condition()
+        self.binary(1)
     }
 
-    fn condition(&mut self) -> ExpressionResult {
-        let left = self.addition()?;
+    /// Precedence climbing: parses operators binding at least as tightly
+    /// as `min_precedence`. Every level is left-associative, so the right
+    /// operand only takes operators binding strictly tighter.
+    fn binary(&mut self, min_precedence: u8) -> ExpressionResult {
+        let mut left = self.factor()?;
 
-        let next = self.current();
-
-        if let Some(token) = next {
-            match token.token_type {
-                TokenType::Equals => (),
-                TokenType::NotEquals => (),
-                TokenType::Greater => (),
-                TokenType::Lesser => (),
-                TokenType::EqGreater => (),
-                TokenType::EqLesser => (),
-                TokenType::And => (),
-                TokenType::Or => (),
-                _ => return Ok(left),
-            }
-
-            // TODO consume etc error handling
-            let operator = self.consume().ok_or(ParserError::eof())?.clone();
-
-            let right = self.condition()?;
-            return Ok(Box::new(ConditionExpression {
-                left,
-                right,
-                operator,
-            }));
-        }
-
-        return Ok(left);
-    }
-
-    fn addition(&mut self) -> ExpressionResult {
-        let left = self.multiplication()?;
-
-        let next = self.current();
-
-        if let Some(token) = next {
-            match token.token_type {
-                TokenType::Plus => (),
-                TokenType::Minus => (),
-                _ => return Ok(left),
-            }
+        loop {
+            let precedence = match self.current().and_then(|t| precedence(&t.token_type)) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
 
             let operator = self.consume().ok_or(ParserError::eof())?.clone();
+            let right = self.binary(precedence + 1)?;
+            let span = left.span().to(right.span());
 
-            let right = self.addition()?;
-            return Ok(Box::new(AdditionExpression {
-                left,
-                right,
-                operator,
-            }));
+            left = match precedence {
+                RANGE => {
+                    // `step` is only a keyword right after a range
+                    let step = if self.current().unwrap_type() == Some(TokenType::Identifier("step".to_string())) {
+                        self.advance();
+                        Some(self.binary(RANGE + 1)?)
+                    } else {
+                        None
+                    };
+
+                    Box::new(RangeExpression {
+                        start: left,
+                        end: right,
+                        inclusive: operator.token_type == TokenType::DotDotEq,
+                        step,
+                        span: self.span_from(span),
+                    })
+                }
+                ADDITIVE => Box::new(AdditionExpression {
+                    left,
+                    right,
+                    operator,
+                    span,
+                }),
+                MULTIPLICATIVE => Box::new(MultiplicationExpression {
+                    left,
+                    right,
+                    operator,
+                    span,
+                }),
+                OR | AND => Box::new(LogicalExpression {
+                    left,
+                    right,
+                    operator,
+                    span,
+                }),
+                _ => Box::new(ConditionExpression {
+                    left,
+                    right,
+                    operator,
+                    span,
+                }),
+            };
         }
 
-        return Ok(left);
+        Ok(left)
     }
 
-    fn multiplication(&mut self) -> ExpressionResult {
-        let left = self.factor()?;
-
-        let next = self.current();
-
-        if let Some(token) = next {
-            match token.token_type {
-                TokenType::Star => (),
-                TokenType::Slash => (),
-                _ => return Ok(left),
-            }
-
-            let operator = self.consume().ok_or(ParserError::eof())?.clone();
-
-            let right = self.multiplication()?;
-            return Ok(Box::new(MultiplicationExpression {
-                left,
-                right,
-                operator,
-            }));
+    fn factor(&mut self) -> ExpressionResult {
+        if self.current().unwrap_type() == Some(TokenType::LeftParen) && self.arrow_parameters_ahead() {
+            let start = self.current().ok_or(ParserError::eof())?.span;
+            let params = self.parameters()?;
+            self.consume().should_be(TokenType::Arrow)?;
+            let lambda = Box::new(LambdaExpression {
+                params,
+                body: self.arrow_body()?,
+                span: self.span_from(start),
+            });
+            return self.call_and_access(lambda);
         }
 
-        return Ok(left);
-    }
-
-    fn factor(&mut self) -> ExpressionResult {
-        let next = self.consume().ok_or(ParserError::eof())?;
+        let next = self.consume().ok_or(ParserError::eof())?.clone();
+        let span = next.span;
 
         let factor: Box<dyn Expression> = match &next.token_type {
-            TokenType::Number(value) => Box::new(ScriptValue::Number(*value)),
-            TokenType::String(string) => Box::new(ScriptValue::String(Rc::new(RefCell::new(
-                string.to_owned(),
-            )))),
+            TokenType::Number(value) => Box::new(LiteralExpression {
+                value: ScriptValue::Number(*value),
+                span,
+            }),
+            TokenType::String(string) => Box::new(LiteralExpression {
+                value: ScriptValue::String(Rc::new(RefCell::new(string.to_owned()))),
+                span,
+            }),
+            TokenType::Template(parts) => self.interpolation(parts, &next)?,
             // These two are very hack-y
             TokenType::Minus => {
                 let operator = next.clone();
                 let expr = self.factor()?;
-                let zero = Box::new(ScriptValue::Number(0.0));
+                let zero = Box::new(LiteralExpression {
+                    value: ScriptValue::Number(0.0),
+                    span,
+                });
 
                 Box::new(
                     AdditionExpression {
                         left: zero,
                         right: expr,
-                        operator
+                        operator,
+                        span: self.span_from(span),
                     }
                 )
             },
             TokenType::Not => {
                 let operator = Token {
                     token_type: TokenType::Equals,
-                    line: next.line,
-                    col: next.col
+                    span,
                 };
 
                 let expr = self.factor()?;
-                let false_val = Box::new(ScriptValue::Boolean(false));
+                let false_val = Box::new(LiteralExpression {
+                    value: ScriptValue::Boolean(false),
+                    span,
+                });
 
                 Box::new(
                     ConditionExpression {
                         left: expr,
                         right: false_val,
-                        operator
+                        operator,
+                        span: self.span_from(span),
                     }
                 )
             }
-            TokenType::Boolean(b) => Box::new(ScriptValue::Boolean(*b)),
-            TokenType::None => Box::new(ScriptValue::None),
+            TokenType::Boolean(b) => Box::new(LiteralExpression {
+                value: ScriptValue::Boolean(*b),
+                span,
+            }),
+            TokenType::None => Box::new(LiteralExpression {
+                value: ScriptValue::None,
+                span,
+            }),
+            TokenType::Done => Box::new(LiteralExpression {
+                value: ScriptValue::Done,
+                span,
+            }),
+            TokenType::Identifier(identifier) if self.lookahead(0) == Some(TokenType::Arrow) => {
+                self.advance();
+                Box::new(LambdaExpression {
+                    params: vec![Parameter::new(identifier)],
+                    body: self.arrow_body()?,
+                    span: self.span_from(span),
+                })
+            }
             TokenType::Identifier(identifier) => {
                 let ident = identifier.to_owned();
 
-                Box::new(VariableExpression { identifier: ident })
+                Box::new(VariableExpression { identifier: ident, span })
             }
             TokenType::LeftParen => {
                 let expr = self.expression()?;
                 self.consume().should_be(TokenType::RightParen)?;
                 expr
             }
+            TokenType::Func => {
+                let params = self.parameters()?;
+                let body = self.function_body()?;
+                Box::new(LambdaExpression {
+                    params,
+                    body: Rc::from(body),
+                    span: self.span_from(span),
+                })
+            }
+            TokenType::LeftBracket
+                if self.object_key_ahead(0) || self.lookahead(0) == Some(TokenType::RightBracket) =>
+            {
+                self.object(span)?
+            }
+            TokenType::LeftBrace => {
+                let items = self.expressions(TokenType::RightBrace)?;
+                Box::new(ListExpression {
+                    items,
+                    span: self.span_from(span),
+                })
+            }
             //_ => panic!("Not a factor: {:?}", next),
-            _ => return Err(ParserError::unexpected(next, "factor"))
+            _ => return Err(ParserError::unexpected(&next, "factor"))
         };
 
         self.call_and_access(factor)
     }
 
-    fn call_and_access(&mut self, base: Box<dyn Expression>) -> ExpressionResult {
-        let call = if let Some(TokenType::LeftParen) = self.current().unwrap_type() {
+    fn while_loop(&mut self, label: Option<String>, start: Span) -> Result<Box<dyn Statement>, ParserError> {
+        self.advance();
+        let condition = self.expression()?;
+
+        self.loops.push(label.clone());
+        let body = self.statement();
+        self.loops.pop();
+
+        Ok(Box::new(WhileStatement {
+            label,
+            condition,
+            body: body?,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// `for x in expr` or `for k, v in expr`, starting from the `for`.
+    fn for_loop(&mut self, label: Option<String>, start: Span) -> Result<Box<dyn Statement>, ParserError> {
+        self.advance();
+        let mut variables = Vec::new();
+        loop {
+            let token = self.consume().ok_or(ParserError::eof())?;
+            match &token.token_type {
+                TokenType::Identifier(ident) => variables.push(ident.to_owned()),
+                _ => return Err(ParserError::unexpected(token, "loop variable")),
+            }
+
+            if variables.len() < 2 && self.current().unwrap_type() == Some(TokenType::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.consume().should_be(TokenType::In)?;
+        let iterable = self.expression()?;
+
+        self.loops.push(label.clone());
+        let body = self.statement();
+        self.loops.pop();
+
+        Ok(Box::new(ForStatement {
+            label,
+            variables,
+            iterable,
+            body: body?,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// `try { } catch (e) { } finally { }`, starting from the `try`. Either
+    /// the catch or the finally part may be left out, but not both.
+    fn try_statement(&mut self, start: Span) -> Result<Box<dyn Statement>, ParserError> {
+        self.advance();
+        let body = self.block()?;
+
+        let catch = if self.current().unwrap_type() == Some(TokenType::Catch) {
             self.advance();
-            let mut params = Vec::new();
-            while let Some(token) = self.current() {
-                if token.token_type == TokenType::RightParen {
-                    break;
+            self.consume().should_be(TokenType::LeftParen)?;
+            let token = self.consume().ok_or(ParserError::eof())?;
+            let variable = match &token.token_type {
+                TokenType::Identifier(ident) => ident.to_owned(),
+                _ => return Err(ParserError::unexpected(token, "identifier")),
+            };
+            self.consume().should_be(TokenType::RightParen)?;
+            Some((variable, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if catch.is_none() || self.current().unwrap_type() == Some(TokenType::Finally) {
+            self.consume().should_be(TokenType::Finally)?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        Ok(Box::new(TryStatement {
+            body,
+            catch,
+            finally,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// `class Name extends Base { method(params) { } }`, starting from the
+    /// `class`.
+    fn class(&mut self, start: Span) -> Result<Box<dyn Statement>, ParserError> {
+        let token = self.advance().ok_or(ParserError::eof())?.clone();
+        let name = match &token.token_type {
+            TokenType::Identifier(name) => name.to_owned(),
+            _ => return Err(ParserError::unexpected(&token, "class name")),
+        };
+
+        let superclass: Option<Box<dyn Expression>> = if self.advance().unwrap_type() == Some(TokenType::Extends) {
+            let token = self.advance().ok_or(ParserError::eof())?.clone();
+            match &token.token_type {
+                TokenType::Identifier(identifier) => {
+                    self.advance();
+                    Some(Box::new(VariableExpression {
+                        identifier: identifier.to_owned(),
+                        span: token.span,
+                    }))
                 }
+                _ => return Err(ParserError::unexpected(&token, "superclass name")),
+            }
+        } else {
+            None
+        };
 
-                let expr = self.expression()?;
-                params.push(expr);
+        self.consume().should_be(TokenType::LeftBracket)?;
+        let mut methods = Vec::new();
+        while self.current().unwrap_type() != Some(TokenType::RightBracket) {
+            let token = self.current().ok_or(ParserError::eof())?.clone();
+            let name = match &token.token_type {
+                TokenType::Identifier(name) => name.to_owned(),
+                _ => return Err(ParserError::unexpected(&token, "method")),
+            };
 
-                if let Some(TokenType::Comma) = self.current().unwrap_type() {
-                    self.consume();
-                } else {
-                    break;
+            self.advance();
+            let params = self.parameters()?;
+            let body = self.function_body()?;
+            methods.push(FunctionStatement {
+                name,
+                params,
+                body: Rc::from(body),
+                span: self.span_from(token.span),
+            });
+        }
+        self.advance();
+
+        Ok(Box::new(ClassStatement {
+            name,
+            superclass,
+            methods,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// A statement that has to be a `{ }` block.
+    fn block(&mut self) -> Result<Box<dyn Statement>, ParserError> {
+        self.current().should_be(TokenType::LeftBracket)?;
+        self.statement()
+    }
+
+    /// Function body, in which `break` and `continue` cannot refer to loops
+    /// outside of the function.
+    fn function_body(&mut self) -> Result<Box<dyn Statement>, ParserError> {
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.statement();
+        self.loops = loops;
+        body
+    }
+
+    /// Parenthesized parameters, starting from the `(`. Parameters after
+    /// one with a default value need one too, and a `...rest` parameter
+    /// has to be the last.
+    fn parameters(&mut self) -> Result<Vec<Parameter>, ParserError> {
+        self.current().should_be(TokenType::LeftParen)?;
+        self.advance();
+        let mut params: Vec<Parameter> = Vec::new();
+        while self.current().unwrap_type() != Some(TokenType::RightParen) {
+            let rest = self.current().unwrap_type() == Some(TokenType::Ellipsis);
+            if rest {
+                self.advance();
+            }
+
+            let token = self.consume().ok_or(ParserError::eof())?.clone();
+            let mut param = match &token.token_type {
+                TokenType::Identifier(ident) => Parameter::new(ident),
+                _ => return Err(ParserError::unexpected(&token, "parameter")),
+            };
+            param.rest = rest;
+
+            if !rest && self.current().unwrap_type() == Some(TokenType::Assign) {
+                self.advance();
+                param.default = Some(Rc::from(self.expression()?));
+            } else if !rest && params.last().is_some_and(|last| last.default.is_some()) {
+                return Err(ParserError::unexpected(&token, "parameter with a default value"));
+            }
+            params.push(param);
+
+            if rest || self.current().unwrap_type() != Some(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.consume().should_be(TokenType::RightParen)?;
+
+        Ok(params)
+    }
+
+    /// Whether the current `(` starts the parameter list of an arrow
+    /// function rather than a parenthesized expression.
+    fn arrow_parameters_ahead(&self) -> bool {
+        // Default values can contain parentheses of their own
+        let mut depth = 0;
+        let mut amount = 1;
+        loop {
+            match self.lookahead(amount) {
+                Some(TokenType::LeftParen) => depth += 1,
+                Some(TokenType::RightParen) if depth == 0 => {
+                    return self.lookahead(amount + 1) == Some(TokenType::Arrow)
                 }
+                Some(TokenType::RightParen) => depth -= 1,
+                Some(_) => (),
+                None => return false,
             }
-            self.consume().should_be(TokenType::RightParen)?;
-            let new_base = Box::new(FunctionExpression { expr: base, params });
+            amount += 1;
+        }
+    }
+
+    /// Body of an arrow function after the `=>`: a block, or a single
+    /// expression that is returned.
+    fn arrow_body(&mut self) -> Result<Rc<dyn Statement>, ParserError> {
+        if self.current().unwrap_type() == Some(TokenType::LeftBracket) && !self.object_key_ahead(1) {
+            return Ok(Rc::from(self.function_body()?));
+        }
+
+        let expr = self.expression()?;
+        let span = expr.span();
+        Ok(Rc::new(ReturnStatement { expr, span }))
+    }
+
+    /// Comma separated expressions up to and including `end`. A trailing
+    /// comma is allowed.
+    fn expressions(&mut self, end: TokenType) -> Result<Vec<Box<dyn Expression>>, ParserError> {
+        let mut exprs = Vec::new();
+        while let Some(token) = self.current() {
+            if token.token_type == end {
+                break;
+            }
+
+            exprs.push(self.expression()?);
+
+            if let Some(TokenType::Comma) = self.current().unwrap_type() {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.consume().should_be(end)?;
+
+        Ok(exprs)
+    }
+
+    /// Call arguments after the opening `(`, up to and including the `)`.
+    /// Named arguments like `retries: 3` have to come after the positional ones.
+    fn arguments(&mut self) -> Result<Vec<Argument>, ParserError> {
+        let mut args: Vec<Argument> = Vec::new();
+        while let Some(token) = self.current() {
+            if token.token_type == TokenType::RightParen {
+                break;
+            }
+
+            let token = token.clone();
+            let name = match (&token.token_type, self.lookahead(1)) {
+                (TokenType::Identifier(name), Some(TokenType::Colon)) => {
+                    self.advance();
+                    self.advance();
+                    Some(name.to_owned())
+                }
+                _ if args.last().is_some_and(|arg| arg.name.is_some()) => {
+                    return Err(ParserError::unexpected(&token, "named argument"));
+                }
+                _ => None,
+            };
+            args.push(Argument {
+                name,
+                expr: self.expression()?,
+            });
+
+            if let Some(TokenType::Comma) = self.current().unwrap_type() {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.consume().should_be(TokenType::RightParen)?;
+
+        Ok(args)
+    }
+
+    /// Object literal fields after the opening `{`, or map literal entries
+    /// if the first key is a computed `[key]`.
+    fn object(&mut self, start: Span) -> ExpressionResult {
+        if self.lookahead(0) == Some(TokenType::LeftBrace) {
+            return self.map(start);
+        }
+
+        let mut fields = Vec::new();
+        while let Some(token) = self.current() {
+            if token.token_type == TokenType::RightBracket {
+                break;
+            }
+
+            let key = match &token.token_type {
+                TokenType::Identifier(key) | TokenType::String(key) => key.to_owned(),
+                _ => return Err(ParserError::unexpected(token, "field name")),
+            };
+            self.advance().should_be(TokenType::Colon)?;
+            self.advance();
+            fields.push((key, self.expression()?));
+
+            if let Some(TokenType::Comma) = self.current().unwrap_type() {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.consume().should_be(TokenType::RightBracket)?;
+
+        Ok(Box::new(ObjectExpression {
+            fields,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Map literal entries after the opening `{`, all with `[key]` keys.
+    fn map(&mut self, start: Span) -> ExpressionResult {
+        let mut entries = Vec::new();
+        while let Some(token) = self.current() {
+            if token.token_type == TokenType::RightBracket {
+                break;
+            }
+
+            self.consume().should_be(TokenType::LeftBrace)?;
+            let key = self.expression()?;
+            self.consume().should_be(TokenType::RightBrace)?;
+            self.consume().should_be(TokenType::Colon)?;
+            entries.push((key, self.expression()?));
+
+            if let Some(TokenType::Comma) = self.current().unwrap_type() {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        self.consume().should_be(TokenType::RightBracket)?;
+
+        Ok(Box::new(MapExpression {
+            entries,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn interpolation(&mut self, parts: &[TemplatePart], string: &Token) -> ExpressionResult {
+        let span = string.span;
+        let mut exprs: Vec<Box<dyn Expression>> = Vec::new();
+
+        for part in parts {
+            match part {
+                TemplatePart::Literal(string) => exprs.push(Box::new(LiteralExpression {
+                    value: ScriptValue::String(Rc::new(RefCell::new(string.to_owned()))),
+                    span,
+                })),
+                TemplatePart::Code(tokens) => {
+                    let mut parser = Parser::new(tokens.clone());
+                    // The embedded tokens end with the `}`, not the line
+                    let expr = parser.expression().map_err(|e| match e.err_type {
+                        ParserErrorType::Eof => ParserError::incomplete_interpolation(string),
+                        _ => e,
+                    })?;
+                    exprs.push(expr);
+                    if let Some(token) = parser.current() {
+                        return Err(ParserError::unexpected(token, "end of interpolation"));
+                    }
+                }
+            }
+        }
+
+        Ok(Box::new(InterpolationExpression { parts: exprs, span }))
+    }
+
+    fn call_and_access(&mut self, base: Box<dyn Expression>) -> ExpressionResult {
+        let start = base.span();
+
+        let call = if let Some(TokenType::LeftParen) = self.current().unwrap_type() {
+            self.advance();
+            let params = self.arguments()?;
+            let new_base = Box::new(FunctionExpression {
+                expr: base,
+                params,
+                span: self.span_from(start),
+            });
             self.call_and_access(new_base)?
         } else {
             base
@@ -353,6 +947,7 @@ impl Parser {
             let new_base = Box::new(IndexExpression {
                 expr: call,
                 index_expr,
+                span: self.span_from(start),
             });
             self.call_and_access(new_base)?
         } else {
@@ -361,18 +956,19 @@ impl Parser {
 
         if let Some(TokenType::Dot) = self.current().unwrap_type() {
             self.advance();
-            let current = self.consume();
-            match current.unwrap_type() {
+            let current = self.consume().cloned();
+            match current.as_ref().unwrap_type() {
                 Some(TokenType::Identifier(ident)) => {
                     let new_base = Box::new(AccessExpression {
                         expr: index,
-                        field: ident.to_owned(),
+                        field: ident,
+                        span: self.span_from(start),
                     });
                     self.call_and_access(new_base)
                 }
                 // Some(other) => panic!("Cannot access {:?}", other),
                 // None => panic!("Unexpected EOF when parsing"),
-                Some(_) => Err(ParserError::unexpected(current.unwrap(), "object")),
+                Some(_) => Err(ParserError::unexpected(current.as_ref().unwrap(), "object")),
                 None => Err(ParserError::eof())
             }
         } else {
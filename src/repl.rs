@@ -0,0 +1,131 @@
+use crate::builtin::create_builtins;
+use crate::environment::Environment;
+use crate::expression::ScriptValue;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::report;
+use crate::scanner::{errors::LexErrorType, Scanner};
+use crate::statement::StatementValue;
+use crate::token::{Span, Token, TokenType};
+
+use std::fmt::Display;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Reads statements from stdin and runs them in one persistent environment.
+/// Input with unclosed brackets, strings or comments is continued on the
+/// next line, and an empty line forces it to be evaluated as is.
+///
+/// Every input is kept, with its spans starting where the previous input's
+/// ended, so errors in functions defined earlier show the right line.
+pub fn run() {
+    let mut env = Environment::new();
+    create_builtins(&mut env);
+    let mut interpreter = Interpreter::new(env);
+
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    let mut source = String::new();
+    let mut inputs = Inputs::default();
+
+    loop {
+        if interactive {
+            print!("{}", if source.is_empty() { "> " } else { "... " });
+            io::stdout().flush().unwrap_or(());
+        }
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let force = line.trim().is_empty();
+        source.push_str(&line);
+        source.push('\n');
+
+        let base = inputs.end();
+        let tokens = match Scanner::new(source.clone()).starting_at(base).scan() {
+            Ok(tokens) if !force && is_incomplete(&tokens) => continue,
+            Ok(tokens) => tokens,
+            Err(e) => match e.err_type {
+                LexErrorType::UnterminatedString | LexErrorType::UnterminatedComment if !force => {
+                    continue
+                }
+                _ => {
+                    report(&e, Some(e.span.rebase(base)), &source);
+                    source.clear();
+                    continue;
+                }
+            },
+        };
+        inputs.push(base, &source);
+
+        let (program, errors) = Parser::new(tokens).parse();
+        for e in &errors {
+            inputs.report(e, e.span());
+        }
+
+        if errors.is_empty() {
+            for stmt in &program {
+                let env = interpreter.env.clone();
+                match stmt.accept(&mut interpreter) {
+                    Ok(StatementValue::Normal(ScriptValue::Unit)) => (),
+                    Ok(StatementValue::Normal(value)) => println!("{}", value),
+                    Ok(_) => (),
+                    Err(e) => {
+                        // Scopes the error left are not exited on the way up
+                        interpreter.env = env;
+                        let e = e.at(stmt.span());
+                        if let Some(traceback) = e.traceback() {
+                            print!("{}", traceback);
+                        }
+                        inputs.report(&e, e.span);
+                        break;
+                    }
+                }
+            }
+        }
+
+        source.clear();
+    }
+}
+
+/// The inputs of the session, each with the offset its spans start at.
+#[derive(Default)]
+struct Inputs {
+    inputs: Vec<(usize, String)>,
+}
+
+impl Inputs {
+    fn end(&self) -> usize {
+        self.inputs.last().map_or(0, |(base, input)| base + input.len())
+    }
+
+    fn push(&mut self, base: usize, input: &str) {
+        self.inputs.push((base, input.to_string()));
+    }
+
+    /// Reports `error` against the input `span` is in.
+    fn report(&self, error: &dyn Display, span: Option<Span>) {
+        let input = span.and_then(|span| {
+            self.inputs
+                .iter()
+                .rev()
+                .find(|(base, _)| span.start >= *base)
+        });
+        match (span, input) {
+            (Some(span), Some((base, input))) => report(error, Some(span.rebase(*base)), input),
+            _ => report(error, None, ""),
+        }
+    }
+}
+
+fn is_incomplete(tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    for token in tokens {
+        match token.token_type {
+            TokenType::LeftBracket | TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+            TokenType::RightBracket | TokenType::RightParen | TokenType::RightBrace => depth -= 1,
+            _ => (),
+        }
+    }
+    depth > 0
+}
@@ -0,0 +1,42 @@
+use crate::token::Span;
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum LexErrorType {
+    UnexpectedCharacter(char),
+    MalformedNumber(String),
+    UnterminatedString,
+    UnterminatedComment,
+    UnknownEscape(char),
+    InvalidUnicodeEscape(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub span: Span,
+    pub err_type: LexErrorType,
+}
+
+impl LexError {
+    pub fn new(span: Span, err_type: LexErrorType) -> LexError {
+        LexError { span, err_type }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}, col {}: ", self.span.line, self.span.col)?;
+
+        match &self.err_type {
+            LexErrorType::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            LexErrorType::MalformedNumber(number) => write!(f, "malformed number {}", number),
+            LexErrorType::UnterminatedString => write!(f, "unterminated string"),
+            LexErrorType::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorType::UnknownEscape(c) => write!(f, "unknown escape sequence \\{}", c),
+            LexErrorType::InvalidUnicodeEscape(digits) => {
+                write!(f, "invalid unicode escape \\u{{{}}}", digits)
+            }
+        }
+    }
+}
@@ -0,0 +1,506 @@
+use crate::token::{Span,TemplatePart,Token,TokenType};
+use errors::*;
+
+use std::collections::HashMap;
+use std::iter::{FromIterator,IntoIterator};
+
+pub mod errors;
+
+type TokenResult = Result<TokenType, LexError>;
+
+pub struct Scanner<'a> {
+    line: usize,
+    col: usize,
+    offset: usize,
+    start: Span,
+    start_index: usize,
+    keep_comments: bool,
+    input: Vec<char>,
+    index: usize,
+    tokens: Vec<Token>,
+    keywords: HashMap<&'a str, TokenType>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(string: String) -> Scanner<'a> {
+        let chars: Vec<char> = string.chars().collect();
+        Scanner {
+            line: 1,
+            col: 0,
+            offset: 0,
+            start: Span::default(),
+            start_index: 0,
+            keep_comments: false,
+            tokens: Vec::new(),
+            input: chars,
+            index: 0,
+            keywords: HashMap::<_, _>::from_iter(IntoIterator::into_iter([
+                ("var", TokenType::Var),
+                ("if", TokenType::If),
+                ("else", TokenType::Else),
+                ("fn", TokenType::Func),
+                ("while", TokenType::While),
+                ("for", TokenType::For),
+                ("in", TokenType::In),
+                ("true", TokenType::Boolean(true)),
+                ("false", TokenType::Boolean(false)),
+                ("null", TokenType::None),
+                ("done", TokenType::Done),
+                ("return", TokenType::Return),
+                ("break", TokenType::Break),
+                ("continue", TokenType::Continue),
+                ("throw", TokenType::Throw),
+                ("try", TokenType::Try),
+                ("catch", TokenType::Catch),
+                ("finally", TokenType::Finally),
+                ("class", TokenType::Class),
+                ("extends", TokenType::Extends),
+            ])),
+        }
+    }
+
+    /// Start the byte offsets of spans at `offset`, for input that follows
+    /// earlier input, like a REPL line does.
+    pub fn starting_at(mut self, offset: usize) -> Scanner<'a> {
+        self.offset = offset;
+        self
+    }
+
+    /// Keep comments as `TokenType::Comment` trivia tokens instead of
+    /// dropping them, for tools that need to reproduce the source.
+    pub fn with_comments(mut self) -> Scanner<'a> {
+        self.keep_comments = true;
+        self
+    }
+
+    fn peek(&self) -> Option<&char> {
+        self.input.get(self.index)
+    }
+
+    fn peek_next(&self) -> Option<&char> {
+        self.input.get(self.index + 1)
+    }
+
+    fn consume(&mut self) -> Option<char> {
+        let next = self.input.get(self.index).copied();
+        if let Some(c) = next {
+            self.index += 1;
+            self.offset += c.len_utf8();
+        }
+        match next {
+            Some('\n') => {
+                self.line += 1;
+                self.col = 0;
+            }
+            Some(_) => self.col += 1,
+            None => (),
+        }
+        next
+    }
+
+    /// Empty span at the scanner position.
+    fn here(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset,
+            line: self.line,
+            col: self.col + 1,
+        }
+    }
+
+    /// Span from the start of the current token to the scanner position.
+    fn span(&self) -> Span {
+        Span {
+            end: self.offset,
+            ..self.start
+        }
+    }
+
+    fn error(&self, err_type: LexErrorType) -> LexError {
+        LexError::new(self.span(), err_type)
+    }
+
+    fn match_or(&mut self, should: char, result: TokenType, default: TokenType) -> TokenType {
+        if let Some(token) = self.peek() {
+            if token == &should {
+                self.consume();
+                return result;
+            }
+        }
+
+        default
+    }
+
+    pub fn scan(mut self) -> Result<Vec<Token>, LexError> {
+        while self.peek().is_some() {
+            if let Some(token) = self.next_token()? {
+                self.tokens.push(token);
+            }
+        }
+        Ok(self.tokens)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        self.start = self.here();
+        self.start_index = self.index;
+
+        let next = match self.consume() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        let token = match next {
+            '+' => TokenType::Plus,
+            '-' => TokenType::Minus,
+            '*' => TokenType::Star,
+            '/' => match self.peek() {
+                Some('/') => self.line_comment(),
+                Some('*') => self.block_comment()?,
+                _ => TokenType::Slash,
+            },
+            '(' => TokenType::LeftParen,
+            ')' => TokenType::RightParen,
+            '{' => TokenType::LeftBracket,
+            '}' => TokenType::RightBracket,
+            '[' => TokenType::LeftBrace,
+            ']' => TokenType::RightBrace,
+            ',' => TokenType::Comma,
+            ':' => TokenType::Colon,
+            '.' => match self.peek() {
+                Some('.') => {
+                    self.consume();
+                    match self.peek() {
+                        Some('.') => {
+                            self.consume();
+                            TokenType::Ellipsis
+                        }
+                        _ => self.match_or('=', TokenType::DotDotEq, TokenType::DotDot),
+                    }
+                }
+                _ => TokenType::Dot,
+            },
+            '=' => match self.peek() {
+                Some('>') => {
+                    self.consume();
+                    TokenType::Arrow
+                }
+                _ => self.match_or('=', TokenType::Equals, TokenType::Assign),
+            },
+            '<' => self.match_or('=', TokenType::EqLesser, TokenType::Lesser),
+            '>' => self.match_or('=', TokenType::EqGreater, TokenType::Greater),
+            '!' => self.match_or('=', TokenType::NotEquals, TokenType::Not),
+            '&' => self.match_or('&', TokenType::And, TokenType::BitAnd),
+            '|' => self.match_or('|', TokenType::Or, TokenType::BitOr),
+            //'\n' => Token::LineBreak,
+            'A'..='Z' | 'a'..='z' | '_' => self.word(next),
+            '0'..='9' => self.number(next)?,
+            '"' => self.string()?,
+            ' ' | '\t' | '\n' | '\r' => TokenType::Nothing,
+            _ => return Err(self.error(LexErrorType::UnexpectedCharacter(next))),
+        };
+
+        if let TokenType::Nothing = token {
+            Ok(None)
+        } else {
+            Ok(Some(Token { token_type: token, span: self.span() }))
+        }
+    }
+
+    fn comment(&self, text: String) -> TokenType {
+        if self.keep_comments {
+            TokenType::Comment(text)
+        } else {
+            TokenType::Nothing
+        }
+    }
+
+    fn line_comment(&mut self) -> TokenType {
+        let mut text = String::from("/");
+
+        while let Some(&c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+
+            self.consume();
+            text.push(c);
+        }
+
+        self.comment(text)
+    }
+
+    fn block_comment(&mut self) -> TokenResult {
+        let mut text = String::from("/");
+        text.extend(self.consume());
+        let mut depth = 1;
+
+        while depth > 0 {
+            let c = match self.consume() {
+                Some(c) => c,
+                None => return Err(self.error(LexErrorType::UnterminatedComment)),
+            };
+            text.push(c);
+
+            match (c, self.peek()) {
+                ('/', Some('*')) => depth += 1,
+                ('*', Some('/')) => depth -= 1,
+                _ => continue,
+            }
+
+            text.extend(self.consume());
+        }
+
+        Ok(self.comment(text))
+    }
+
+    fn string(&mut self) -> TokenResult {
+        let mut s = String::new();
+        let mut parts = Vec::new();
+
+        loop {
+            let escape_start = self.here();
+            match self.consume() {
+                Some('"') => break,
+                Some('\\') => s.push(self.escape(escape_start)?),
+                Some('$') if self.peek() == Some(&'{') => {
+                    self.consume();
+                    parts.push(TemplatePart::Literal(s));
+                    parts.push(TemplatePart::Code(self.interpolation()?));
+                    s = String::new();
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error(LexErrorType::UnterminatedString)),
+            }
+        }
+
+        if parts.is_empty() {
+            Ok(TokenType::String(s))
+        } else {
+            parts.push(TemplatePart::Literal(s));
+            Ok(TokenType::Template(parts))
+        }
+    }
+
+    /// Scans the tokens of a `${...}` expression, up to the matching `}`.
+    fn interpolation(&mut self) -> Result<Vec<Token>, LexError> {
+        let start = self.start;
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some('}') if depth == 0 => {
+                    self.consume();
+                    break;
+                }
+                Some(_) => (),
+                None => return Err(self.error(LexErrorType::UnterminatedString)),
+            }
+
+            let token = self.next_token()?;
+            self.start = start;
+
+            if let Some(token) = token {
+                match token.token_type {
+                    TokenType::LeftBracket => depth += 1,
+                    TokenType::RightBracket => depth -= 1,
+                    _ => (),
+                }
+                tokens.push(token);
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    fn escape(&mut self, start: Span) -> Result<char, LexError> {
+        let escaped = match self.consume() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('$') => '$',
+            Some('u') => return self.unicode_escape(start),
+            Some(c) => {
+                let span = Span { end: self.offset, ..start };
+                return Err(LexError::new(span, LexErrorType::UnknownEscape(c)));
+            }
+            None => return Err(self.error(LexErrorType::UnterminatedString)),
+        };
+
+        Ok(escaped)
+    }
+
+    /// Parses the `{XXXX}` part of a `\u{XXXX}` escape.
+    fn unicode_escape(&mut self, start: Span) -> Result<char, LexError> {
+        let mut digits = String::new();
+        let mut terminated = false;
+
+        if let Some('{') = self.peek() {
+            self.consume();
+            while let Some(&c) = self.peek() {
+                if c == '"' {
+                    break;
+                }
+
+                self.consume();
+                if c == '}' {
+                    terminated = true;
+                    break;
+                }
+                digits.push(c);
+            }
+        }
+
+        let escaped = if terminated && !digits.is_empty() && digits.len() <= 6 {
+            u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+        } else {
+            None
+        };
+
+        escaped.ok_or_else(|| {
+            let span = Span { end: self.offset, ..start };
+            LexError::new(span, LexErrorType::InvalidUnicodeEscape(digits))
+        })
+    }
+
+    fn word(&mut self, first: char) -> TokenType {
+        let mut s = String::from(first);
+
+        while let Some(&c) = self.peek() {
+            match c {
+                'A'..='Z' | 'a'..='z' | '0'..='9' | '_' => {
+                    self.consume();
+                    s.push(c);
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(keyword) = self.keywords.get(&*s) {
+            keyword.clone()
+        } else {
+            TokenType::Identifier(s)
+        }
+    }
+
+    fn number(&mut self, first: char) -> TokenResult {
+        if first == '0' {
+            let radix = match self.peek() {
+                Some('x') | Some('X') => Some(16),
+                Some('o') | Some('O') => Some(8),
+                Some('b') | Some('B') => Some(2),
+                _ => None,
+            };
+
+            if let Some(radix) = radix {
+                self.consume();
+                return self.radix_number(radix);
+            }
+        }
+
+        let mut s = String::from(first);
+        self.digits(&mut s);
+
+        if self.peek() == Some(&'.') && self.peek_next().is_some_and(char::is_ascii_digit) {
+            s.extend(self.consume());
+            self.digits(&mut s);
+        }
+
+        if let Some('e') | Some('E') = self.peek() {
+            s.extend(self.consume());
+            if let Some('+') | Some('-') = self.peek() {
+                s.extend(self.consume());
+            }
+            if !self.peek().is_some_and(char::is_ascii_digit) {
+                return Err(self.malformed_number());
+            }
+            self.digits(&mut s);
+        }
+
+        if self.peek() == Some(&'.') && self.peek_next().is_some_and(char::is_ascii_digit) {
+            return Err(self.malformed_number());
+        }
+
+        if !separated(&s, char::is_ascii_digit) {
+            return Err(self.malformed_number());
+        }
+
+        // Too large numbers would silently become infinity
+        let n = s.replace('_', "").parse::<f64>();
+        match n {
+            Ok(n) if n.is_finite() && !self.peek().is_some_and(|c| c.is_alphanumeric()) => {
+                Ok(TokenType::Number(n))
+            }
+            _ => Err(self.malformed_number()),
+        }
+    }
+
+    /// Number after a `0x`, `0o` or `0b` prefix.
+    fn radix_number(&mut self, radix: u32) -> TokenResult {
+        let mut s = String::new();
+
+        while let Some(&c) = self.peek() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+
+            self.consume();
+            s.push(c);
+        }
+
+        // Fractions only exist for decimal numbers
+        if self.peek() == Some(&'.')
+            && self.peek_next().is_some_and(|c| c.is_ascii_digit() || c.is_digit(radix))
+        {
+            return Err(self.malformed_number());
+        }
+
+        if !separated(&s, char::is_ascii_alphanumeric) {
+            return Err(self.malformed_number());
+        }
+
+        let digits = s.replace('_', "");
+        match u64::from_str_radix(&digits, radix) {
+            Ok(n) => Ok(TokenType::Number(n as f64)),
+            _ => Err(self.malformed_number()),
+        }
+    }
+
+    fn digits(&mut self, s: &mut String) {
+        while let Some(&c) = self.peek() {
+            match c {
+                '0'..='9' | '_' => {
+                    self.consume();
+                    s.push(c);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Error for the malformed number being scanned, spanning the rest of
+    /// the number-like characters so the whole literal gets reported.
+    fn malformed_number(&mut self) -> LexError {
+        while let Some(&c) = self.peek() {
+            if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                break;
+            }
+            self.consume();
+        }
+
+        let literal = self.input[self.start_index..self.index].iter().collect();
+        self.error(LexErrorType::MalformedNumber(literal))
+    }
+}
+
+/// Whether every `_` in the number `s` separates two digits, so that `1_000`
+/// is fine but `1_`, `1__000` and `0x_1` are not.
+fn separated(s: &str, digit: fn(&char) -> bool) -> bool {
+    let chars: Vec<char> = s.chars().collect();
+    chars.iter().enumerate().all(|(i, c)| {
+        *c != '_' || (i > 0 && digit(&chars[i - 1]) && chars.get(i + 1).is_some_and(digit))
+    })
+}
@@ -1,5 +1,7 @@
 use crate::expression::*;
+use crate::function::Parameter;
 use crate::interpreter::*;
+use crate::token::Span;
 
 use std::rc::Rc;
 
@@ -9,106 +11,259 @@ pub trait StatementVisitor {
     fn visit_if(&mut self, stmt: &IfStatement) -> StatementResult;
     fn visit_function(&mut self, stmt: &FunctionStatement) -> StatementResult;
     fn visit_while(&mut self, stmt: &WhileStatement) -> StatementResult;
+    fn visit_for(&mut self, stmt: &ForStatement) -> StatementResult;
     fn visit_block(&mut self, stmt: &BlockStatement) -> StatementResult;
     fn visit_expression(&mut self, stmt: &ExpressionStatement) -> StatementResult;
     fn visit_return(&mut self, stmt: &ReturnStatement) -> StatementResult;
+    fn visit_break(&mut self, stmt: &BreakStatement) -> StatementResult;
+    fn visit_continue(&mut self, stmt: &ContinueStatement) -> StatementResult;
+    fn visit_throw(&mut self, stmt: &ThrowStatement) -> StatementResult;
+    fn visit_try(&mut self, stmt: &TryStatement) -> StatementResult;
+    fn visit_class(&mut self, stmt: &ClassStatement) -> StatementResult;
     fn visit_internal(&mut self, stmt: &InternalStatement) -> StatementResult;
 }
 
 pub enum StatementValue {
     Normal(ScriptValue),
     Return(ScriptValue),
+    Break(Option<String>),
+    Continue(Option<String>),
 }
 
 pub trait Statement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult;
+    fn span(&self) -> Span;
+
+    /// Whether the statement is the Rust body of a built-in function.
+    fn is_internal(&self) -> bool {
+        false
+    }
 }
 
 pub struct DeclarationStatement {
     pub variables: Vec<String>,
     pub initializer: Option<Box<dyn Expression>>,
+    pub span: Span,
 }
 
 impl Statement for DeclarationStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_declaration(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct AssignmentStatement {
     pub assignee: Box<dyn Expression>,
     pub expr: Box<dyn Expression>,
+    pub span: Span,
 }
 
 impl Statement for AssignmentStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_assignment(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct IfStatement {
     pub condition: Box<dyn Expression>,
     pub if_body: Box<dyn Statement>,
     pub else_body: Option<Box<dyn Statement>>,
+    pub span: Span,
 }
 
 impl Statement for IfStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_if(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct WhileStatement {
+    pub label: Option<String>,
     pub condition: Box<dyn Expression>,
     pub body: Box<dyn Statement>,
+    pub span: Span,
 }
 
 impl Statement for WhileStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_while(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct ForStatement {
+    pub label: Option<String>,
+    pub variables: Vec<String>,
+    pub iterable: Box<dyn Expression>,
+    pub body: Box<dyn Statement>,
+    pub span: Span,
+}
+
+impl Statement for ForStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_for(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct FunctionStatement {
     pub name: String,
-    pub params: Vec<String>,
+    pub params: Vec<Parameter>,
     pub body: Rc<dyn Statement>,
+    pub span: Span,
 }
 
 impl Statement for FunctionStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_function(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct ExpressionStatement {
     pub expr: Box<dyn Expression>,
+    pub span: Span,
 }
 
 impl Statement for ExpressionStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_expression(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct BlockStatement {
     pub body: Vec<Box<dyn Statement>>,
+    pub span: Span,
 }
 
 impl Statement for BlockStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_block(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub struct ReturnStatement {
     pub expr: Box<dyn Expression>,
+    pub span: Span,
 }
 
 impl Statement for ReturnStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_return(self)
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct BreakStatement {
+    pub label: Option<String>,
+    pub span: Span,
+}
+
+impl Statement for BreakStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_break(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct ContinueStatement {
+    pub label: Option<String>,
+    pub span: Span,
+}
+
+impl Statement for ContinueStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_continue(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct ThrowStatement {
+    pub expr: Box<dyn Expression>,
+    pub span: Span,
+}
+
+impl Statement for ThrowStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_throw(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct TryStatement {
+    pub body: Box<dyn Statement>,
+    /// Variable the caught error is bound to, and the catch body
+    pub catch: Option<(String, Box<dyn Statement>)>,
+    pub finally: Option<Box<dyn Statement>>,
+    pub span: Span,
+}
+
+impl Statement for TryStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_try(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+pub struct ClassStatement {
+    pub name: String,
+    pub superclass: Option<Box<dyn Expression>>,
+    pub methods: Vec<FunctionStatement>,
+    pub span: Span,
+}
+
+impl Statement for ClassStatement {
+    fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
+        visitor.visit_class(self)
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 pub type InternalFunction = fn(interpreter: &mut Interpreter) -> StatementResult;
@@ -121,4 +276,12 @@ impl Statement for InternalStatement {
     fn accept(&self, visitor: &mut dyn StatementVisitor) -> StatementResult {
         visitor.visit_internal(self)
     }
+
+    fn span(&self) -> Span {
+        Span::default()
+    }
+
+    fn is_internal(&self) -> bool {
+        true
+    }
 }
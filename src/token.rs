@@ -5,12 +5,14 @@ pub enum TokenType {
     Identifier(String),
     Number(f64),
     String(String),
+    Template(Vec<TemplatePart>),
     Boolean(bool),
     Plus,
     Minus,
     Star,
     Slash,
     Assign,
+    Arrow,
     And,
     Or,
     Not,
@@ -28,6 +30,8 @@ pub enum TokenType {
     If,
     Else,
     While,
+    For,
+    In,
     LeftBracket,
     RightBracket,
     LeftParen,
@@ -35,18 +39,101 @@ pub enum TokenType {
     LeftBrace,
     RightBrace,
     Comma,
+    Colon,
     Dot,
+    DotDot,
+    DotDotEq,
+    Ellipsis,
     Return,
+    Break,
+    Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Class,
+    Extends,
     None,
+    Done,
+    Comment(String),
     // TODO better solution,
     Nothing,
 }
 
-#[derive(Clone, Debug)]
+/// A region of the source: byte offsets `start..end`, plus the line and
+/// column (both starting from 1) where it begins.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// Span covering both `self` and `other`, which should come after it.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end.max(self.end),
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    /// The span with its offsets made relative to `base`.
+    pub fn rebase(&self, base: usize) -> Span {
+        Span {
+            start: self.start.saturating_sub(base),
+            end: self.end.saturating_sub(base),
+            ..*self
+        }
+    }
+
+    /// Renders the first source line of the span with the spanned part
+    /// underlined, in the style of rustc.
+    pub fn underline(&self, source: &str) -> String {
+        let line = match source.lines().nth(self.line.saturating_sub(1)) {
+            Some(line) => line,
+            None => return String::new(),
+        };
+
+        // Spans from somewhere else can fall inside a character here
+        let start = (0..=self.start.min(source.len()))
+            .rev()
+            .find(|&i| source.is_char_boundary(i))
+            .unwrap_or(0);
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.end.min(line_start + line.len());
+        let width = source.get(start..end).map_or(0, |s| s.chars().count());
+
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        format!(
+            "{} |\n{} | {}\n{} | {}{}\n",
+            gutter,
+            number,
+            line,
+            gutter,
+            " ".repeat(self.col.saturating_sub(1)),
+            "^".repeat(width.max(1))
+        )
+    }
+}
+
+/// Piece of an interpolated string: either literal text or the tokens of
+/// an embedded `${...}` expression.
+#[derive(PartialEq, Clone, Debug)]
+pub enum TemplatePart {
+    Literal(String),
+    Code(Vec<Token>),
+}
+
+#[derive(PartialEq, Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
-    pub line: usize,
-    pub col: usize
+    pub span: Span,
 }
 
 pub struct Tokens {
@@ -57,11 +144,19 @@ pub struct Tokens {
 impl Tokens {
     pub fn new(vec: Vec<Token>) -> Tokens {
         Tokens {
-            input: vec,
+            // Comments are trivia, the parser never needs to see them
+            input: vec
+                .into_iter()
+                .filter(|t| !matches!(t.token_type, TokenType::Comment(_)))
+                .collect(),
             index: 0,
         }
     }
 
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
     pub fn current(&self) -> Option<&Token> {
         self.input.get(self.index)
     }
@@ -72,13 +167,25 @@ impl Tokens {
         self.input.get(curr)
     }
 
+    pub fn previous(&self) -> Option<&Token> {
+        self.index.checked_sub(1).and_then(|i| self.input.get(i))
+    }
+
+    pub fn step_back(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    pub fn skip_to(&mut self, index: usize) {
+        self.index = index;
+    }
+
     pub fn advance(&mut self) -> Option<&Token> {
         self.index += 1;
         self.input.get(self.index)
     }
 
-    pub fn _peek(&self) -> Option<&Token> {
-        self.input.get(self.index + 1)
+    pub fn peek(&self, amount: usize) -> Option<&Token> {
+        self.input.get(self.index + amount)
     }
 
     pub fn _skip(&mut self, amount: usize) -> Option<&Token> {
@@ -89,7 +196,7 @@ impl Tokens {
 
 pub trait TokenTrait {
     fn should_be(&self, other: TokenType) -> Result<(), ParserError>;
-    fn might_be(&self, other: TokenType) -> Option<()>;
+    fn _might_be(&self, other: TokenType) -> Option<()>;
     fn unwrap_type(&self) -> Option<TokenType>;
 
 }
@@ -110,7 +217,7 @@ impl TokenTrait for Option<&Token> {
         Ok(())
     }
 
-    fn might_be(&self, other: TokenType) -> Option<()> {
+    fn _might_be(&self, other: TokenType) -> Option<()> {
         if let Some(t) = self {
             if t.token_type != other {
                 return None;
@@ -123,10 +230,6 @@ impl TokenTrait for Option<&Token> {
     }
 
     fn unwrap_type(&self) -> Option<TokenType> {
-        if let Some(t) = self {
-            Some(t.token_type.clone())
-        } else {
-            None
-        }
+        self.as_ref().map(|t| t.token_type.clone())
     }
 }
@@ -1,10 +1,19 @@
 import subprocess
 
 
-def run_and_compare(file, res_should):
+def run_and_compare(file, res_should, repl=False, flags=()):
     res_should.append('')
-    result = subprocess.run(['cargo', 'run', './scripts/' + file + '.script'], stdout=subprocess.PIPE, encoding="UTF8", stderr=subprocess.PIPE)
+    path = './scripts/' + file + '.script'
+    if repl:
+        with open(path) as script:
+            result = subprocess.run(['cargo', 'run'], stdin=script, stdout=subprocess.PIPE, encoding="UTF8", stderr=subprocess.PIPE)
+    else:
+        result = subprocess.run(['cargo', 'run', '--', *flags, path], stdout=subprocess.PIPE, encoding="UTF8", stderr=subprocess.PIPE)
     split = str(result.stdout).split('\n')
+    # Rust exits with 101 when it panics
+    if result.returncode == 101:
+        print('%s test panicked: %s' % (file, result.stderr.strip().split('\n')[-1]))
+        return False
     try:
         assert split == res_should
         return True
@@ -17,12 +26,135 @@ tests = [
     ['exp', ['4294967296', '256']],
     ['fibonacci', ['55']],
     ['counter', ['-3', '201']],
-    ['object', ['o1', 'o2', 'o3', 'o2']]
+    ['object', ['o1', 'o2', 'o3', 'o2']],
+    ['lex_error', ['Line 2, col 9: malformed number 1.2.3', '  |', '2 | var x = 1.2.3', '  |         ^^^^^']],
+    ['runtime_error', ['3', 'Line 3, col 15: Variable not found: undefined', '  |', '3 | print(x + 1 + undefined)', '  |               ^^^^^^^^^']],
+    ['comments', ['0.5', '1', 'Line 9, col 7: Variable not found: undefined', '  |', '9 | print(undefined)', '  |       ^^^^^^^^^']],
+    ['comment_tokens', [
+        '1:1 Comment("// line comment")', '2:1 Var', '2:5 Identifier("x")', '2:7 Assign', '2:9 Number(1.0)',
+        '2:11 Comment("/* block /* nested */\\nspans lines */")', '3:16 Plus', '3:18 Number(2.0)'
+    ], False, ['--tokens']],
+    ['strings', ['tab:\tend', 'quote: "hi" backslash: \\', 'snow\u2603man', 'first', 'second', 'Line 6, col 7: Variable not found: missing', '  |', '6 | print(missing)', '  |       ^^^^^^^']],
+    ['interpolation', ['hello world!', 'x = 5, x * 2 = 10', 'nested: inner 6', 'escaped: ${x}', 'hi you']],
+    ['interpolation_errors', [
+        'Line 1, col 7: incomplete expression in interpolation', '  |', '1 | print("${}")', '  |       ^^^^^',
+        'Line 2, col 9: incomplete expression in interpolation', '  |', '2 | var a = "a ${1 +} b"', '  |         ^^^^^^^^^^^^'
+    ]],
+    ['numbers', ['255', '10', '15', '1000000', '0.0015', '2000.25', '65535']],
+    ['bad_number', ['Line 1, col 12: malformed number 0b12', '  |', '1 | var mask = 0b12', '  |            ^^^^']],
+    ['precedence', ['5', '1', '14', '4', 'true', 'true', 'true', 'true', 'true', 'true', 'false']],
+    ['short_circuit', ['x is null', 'false', 'true', '0', 'true', 'true', '2']],
+    ['parse_errors', [
+        'Line 2, col 1: expected factor, found Var', '  |', '2 | var b = (2', '  | ^^^',
+        'Line 3, col 1: expected RightParen, found Func', '  |', '3 | fn f(x) {', '  | ^^',
+        'Line 4, col 12: expected factor, found RightParen', '  |', '4 |     return )', '  |            ^',
+        'Line 6, col 11: expected factor, found LeftBracket', '  |', '6 | while a < {', '  |           ^',
+        'Line 10, col 5: expected identifier, found Done', '   |', '10 | var done = 3', '   |     ^^^^',
+        'Line 11, col 18: expected factor, found RightBracket', '   |', '11 | var c = { x: 1 + }', '   |                  ^',
+        'Line 14, col 1: expected factor, found RightBracket', '   |', '14 | }', '   | ^'
+    ]],
+    ['lists', ['[1, 2, 3]', '[[1, 2], [], [a, [true]]]', '4', '4', '[0, 1, 2, 3]', '1', '[2, 3]', 'true', 'false', '2', '-1', '3 - 2 - 0', '2', '[2, 4]',
+        '[1, [...]]', '[{ me: {...}, name: o }, { me: {...}, name: o }]',
+        'Traceback (most recent call last):', '  Line 28, col 1, in <script>', '  Line 28, col 1, in remove',
+        'Line 28, col 1: Index out of bounds: 5', '   |', '28 | l.remove(5)', '   | ^^^^^^^^^^^']],
+    ['object_literal', ['o1', '3', 'o1 has 3', '[1, 2]', 'o1 has 4', '{  }', 'still a block']],
+    ['lambdas', ['[2, 4, 6]', '[2, 3, 4]', '5', '42', 'negative', '15', '49', '2', '9']],
+    ['loop_control', ['1', '3', '4', '0', '[2, 3]', '30']],
+    ['loop_errors', [
+        'Line 1, col 1: break outside of a loop', '  |', '1 | break', '  | ^^^^^',
+        'Line 4, col 9: continue outside of a loop', '  |', '4 |         continue', '  |         ^^^^^^^^',
+        'Line 6, col 5: no enclosing loop labeled nowhere', '  |', '6 |     break nowhere', '  |     ^^^^^'
+    ]],
+    ['for_loops', [
+        '1', '2', '3', '0: h', '1: e', '2: y', '0..5', '0..=5', '0..10 step 3', '5..0 step -2', '0..2',
+        'a', 'b', 'a = 1', 'b = 2', '10', '20', '30', '8', '0', '1', '[2, 4, 6]',
+        '0..1000000000000', '0..=1000000000000 step 2', '1', '9', 'Index out of bounds: 5', 'ZeroStep: Range step cannot be 0',
+        'Line 64, col 10: Not iterable: 5', '   |', '64 | for x in 5 {', '   |          ^'
+    ]],
+    ['iterators', [
+        '3', '2', '1', '0: 3', '1: 2', '2: 1', '1', '2', '3', '[16, 9, 4, 1]', '[true, false]', 'done', 'true',
+        'Line 44, col 10: Not iterable: { iter: Func }', '   |', '44 | for x in { iter: fn() { return 1 } } {', '   |          ^^^^^^^^^^^^^^^^^^^^^^^^^^^'
+    ]],
+    ['exceptions', [
+        '1', '3', 'PropertyNotFound', 'Property not found: { a: 1 }', '18', 'NotIndexable', 'finally', 'cleaning up', 'try',
+        'again', 'again', '3', 'inner finally', 'caught inner',
+        'Line 70, col 5: Uncaught: Variable not found: undefined', '   |', '70 |     throw e.message', '   |     ^^^^^^^^^^^^^^^'
+    ]],
+    ['traceback', [
+        'Not callable: 1', 'Traceback (most recent call last):', '  Line 19, col 9, in <script>', '  Line 16, col 18, in <lambda>',
+        '  Line 6, col 18, in middle', '  Line 2, col 12, in inner', 'Line 2, col 12: Not callable: 5', '  |', '2 |     return x()', '  |            ^^^'
+    ]],
+    ['errors', [
+        'UndefinedVariable: Variable not found: undefined_variable', 'NotComparable: Cannot compare: 1 and a',
+        'NotComparable: Cannot compare: true and false', 'NotObject: Not an object: 5', 'NotIndexable: Not indexable: 5',
+        'InvalidIndex: Cannot index with: a', 'IndexOutOfBounds: Index out of bounds: 2', 'IndexOutOfBounds: Index out of bounds: 5',
+        'IndexOutOfBounds: Index out of bounds: -1', 'InvalidIndex: Cannot index with: 0.5', 'NotCallable: Not callable: 5',
+        'NotIterable: Not iterable: 5', 'InvalidSize: Not a list size: 100000000000000000000',
+        'InvalidSize: Not a list size: -1', 'InvalidSize: Not a list size: NaN', 'InvalidSize: Not a list size: 1.5',
+        'NotNumber: Not a number: a', 'NotBoolean: Not a boolean: 1', 'NotBoolean: Not a boolean: 1',
+        'InvalidRangeBound: Not a range bound: a', 'UndefinedVariable: Variable not found: undefined_object', 'still running',
+        '[1, 2]', '[1]'
+    ]],
+    ['assignment_errors', [
+        'Line 2, col 1: invalid assignment target', '  |', '2 | 1 = 2', '  | ^',
+        'Line 3, col 1: invalid assignment target', '  |', '3 | print(a) = 3', '  | ^^^^^^^^',
+        'Line 4, col 1: invalid assignment target', '  |', '4 | a + 1 = 4', '  | ^^^^^'
+    ]],
+    ['parameters', [
+        'Hello Ada true', 'Hi Ada false', 'Hi Ada !', '1', '6', '[]', '[1, 2, 3]', '8', '[]',
+        'Arity: greet takes 1 to 3 arguments, got 0', 'Arity: greet takes 1 to 3 arguments, got 4',
+        'Arity: sum takes at least 1 argument, got 0', 'Arity: <lambda> takes 1 to 2 arguments, got 3',
+        'Arity: print takes 1 argument, got 0'
+    ]],
+    ['parameter_errors', [
+        'Line 1, col 13: expected parameter with a default value, found Identifier("b")', '  |', '1 | fn f(a = 1, b) {', '  |             ^',
+        'Line 4, col 13: expected RightParen, found Comma', '  |', '4 | fn g(...rest, last) {', '  |             ^',
+        'Line 7, col 13: expected parameter, found Number(1.0)', '  |', '7 | var h = (a, 1) => a', '  |             ^'
+    ]],
+    ['named_arguments', [
+        'example.org:80 retries=1 verbose=false', 'example.org:80 retries=3 verbose=true', 'localhost:8080 retries=1 verbose=false',
+        'localhost:22 retries=1 verbose=true', 'info [a, b]', 'warn []', '[null, null]', '[0, 1, 2]', '[2, 4]',
+        'UnknownArgument: connect has no parameter named timeout', 'DuplicateArgument: connect got argument host more than once',
+        'DuplicateArgument: connect got argument port more than once', 'MissingArgument: connect is missing argument host',
+        'UnknownArgument: log has no parameter named messages'
+    ]],
+    ['named_argument_errors', ['Line 4, col 9: expected named argument, found Number(2.0)', '  |', '4 | f(a: 1, 2)', '  |         ^']],
+    ['classes', [
+        'rect with area 6', 'square with area 16!', 'Square { h: 4, name: square, w: 4 }', '16', 'true', 'true', 'false', 'false',
+        'Class Rect', 'Empty {  }', '1', '2', '3', 'Arity: Empty takes 0 arguments, got 1', 'Arity: Rect.init takes 2 arguments, got 1',
+        'NotClass: Not a class: 5', 'PropertyNotFound: Property not found: Rect { h: 3, name: rect, w: 2 }',
+        'Line 84, col 22: Not a class: 5', '   |', '84 | class Broken extends NotAClass {', '   |                      ^^^^^^^^^'
+    ]],
+    ['methods', [
+        'hi from a', 'hi from b', 'hi from a', 'c!', 'rex barks', 'fido barks', 'rex barks', 'fido barks', 'max barks',
+        'holder barks softly'
+    ]],
+    ['maps', ['one', '2', 'yes', 'nothing', '{ [null]: nothing, [true]: yes, [1]: one, [two]: 2 }', 'two', '3', '5', 'true', 'false', 'true', 'false', '[null, true, 1, two]', '[nothing, yes, one, 3]', '[[null, nothing], [true, yes], [1, one], [two, 3]]', 'nan', 'negative zero', '3', '0 -> negative zero', '1.5 -> fraction', 'NaN -> nan', 'a', 'b', 'true', 'false', '[2, 4]', 'KeyNotFound: Key not found: missing', 'NotHashable: Cannot use as a key: [1]', 'NotHashable: Cannot use as a key: {  }', 'NotHashable: Cannot use as a key: { [null]: nothing, [true]: yes, [1]: one, [two]: 3 }', 'PropertyNotFound: Property not found: { [null]: nothing, [true]: yes, [1]: one, [two]: 3 }']],
+    ['bad_numbers', [
+        '1000000', '65535',
+        'Line 1, col 7: malformed number 0x1.5', '  |', '1 | print(0x1.5)', '  |       ^^^^^',
+        'Line 1, col 7: malformed number 0b1.1', '  |', '1 | print(0b1.1)', '  |       ^^^^^',
+        'Line 1, col 7: malformed number 1_', '  |', '1 | print(1_)', '  |       ^^',
+        'Line 1, col 7: malformed number 1__000', '  |', '1 | print(1__000)', '  |       ^^^^^^',
+        'Line 1, col 7: malformed number 0x_', '  |', '1 | print(0x_)', '  |       ^^^',
+        'Line 1, col 7: malformed number 0x_1', '  |', '1 | print(0x_1)', '  |       ^^^^',
+        'Line 1, col 7: malformed number 1_.5', '  |', '1 | print(1_.5)', '  |       ^^^^',
+        'Line 1, col 7: malformed number 1e_5', '  |', '1 | print(1e_5)', '  |       ^^^^',
+        'Line 1, col 7: malformed number 1e400', '  |', '1 | print(1e400)', '  |       ^^^^^',
+        '0'
+    ], True],
+    ['repl', ['6', '3', 'Line 1, col 1: Variable not found: undefined', '  |', '1 | undefined + 1', '  | ^^^^^^^^^', 'multi', 'line', '3',
+        'Line 3, col 5: Variable not found: undefined', '  |', '3 |     undefined', '  |     ^^^^^^^^^',
+        'Line 1, col 1: Variable not found: inner', '  |', '1 | inner', '  | ^^^^^',
+        'Traceback (most recent call last):', '  Line 1, col 28, in <script>', '  Line 1, col 22, in broken',
+        'Line 1, col 22: Variable not found: undefined_thing', '  |', '1 | fn broken() { return undefined_thing }',
+        '  |                      ^^^^^^^^^^^^^^^', 'still running'
+    ], True]
 ]
 
 successes = 0
 for test in tests:
-    if run_and_compare(test[0], test[1]):
+    if run_and_compare(*test):
         successes += 1
 
 print("\n%d / %d tests succeeded" % (successes, len(tests)))
\ No newline at end of file